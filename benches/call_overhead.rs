@@ -0,0 +1,159 @@
+// benches/call_overhead.rs
+
+//! Benchmarks for `McpToolAdapter::call` around a mock client.
+//!
+//! The mock client answers every call immediately, so the measurements are
+//! dominated by the adapter's own call path: argument validation, the
+//! cancellation guard and how the client future is driven.
+//!
+//! To compare the call path before and after a change, save a baseline on
+//! the old revision and compare against it on the new one. Revisions from
+//! before this benchmark was added don't have it, so copy this file onto the
+//! old revision first, along with its `[[bench]]` entry and dev-dependencies
+//! from `Cargo.toml`. Sharing the target directory keeps both runs' results
+//! in one place:
+//!
+//! ```text
+//! git worktree add ../old <old>
+//! cp benches/call_overhead.rs ../old/benches/
+//! (cd ../old && CARGO_TARGET_DIR="$OLDPWD/target" cargo bench --bench call_overhead -- --save-baseline old)
+//! cargo bench --bench call_overhead -- --baseline old
+//! ```
+//!
+//! Revisions where adapters took `Arc<Box<dyn McpClientTrait>>` also need
+//! `adapter()` changed to build the client as `Arc::new(Box::new(MockClient))`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mcp_client::{
+    client::{ClientCapabilities, ClientInfo},
+    Error as McpClientError, McpClientTrait,
+};
+use mcp_rig::{McpToolAdapter, McpToolArgs};
+use mcp_spec::{
+    protocol::{
+        CallToolResult, GetPromptResult, InitializeResult, ListPromptsResult, ListResourcesResult,
+        ListToolsResult, ReadResourceResult,
+    },
+    Content,
+};
+use rig::tool::Tool;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+/// Client that echoes the arguments of every tool call.
+struct MockClient;
+
+#[async_trait::async_trait]
+impl McpClientTrait for MockClient {
+    async fn initialize(
+        &mut self,
+        _info: ClientInfo,
+        _capabilities: ClientCapabilities,
+    ) -> Result<InitializeResult, McpClientError> {
+        Err(McpClientError::NotReady)
+    }
+
+    async fn list_resources(
+        &self,
+        _next_cursor: Option<String>,
+    ) -> Result<ListResourcesResult, McpClientError> {
+        Err(McpClientError::NotReady)
+    }
+
+    async fn read_resource(&self, _uri: &str) -> Result<ReadResourceResult, McpClientError> {
+        Err(McpClientError::NotReady)
+    }
+
+    async fn list_tools(
+        &self,
+        _next_cursor: Option<String>,
+    ) -> Result<ListToolsResult, McpClientError> {
+        Ok(ListToolsResult {
+            tools: Vec::new(),
+            next_cursor: None,
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        _name: &str,
+        arguments: Value,
+    ) -> Result<CallToolResult, McpClientError> {
+        Ok(CallToolResult {
+            content: vec![Content::text(arguments.to_string())],
+            is_error: None,
+        })
+    }
+
+    async fn list_prompts(
+        &self,
+        _next_cursor: Option<String>,
+    ) -> Result<ListPromptsResult, McpClientError> {
+        Err(McpClientError::NotReady)
+    }
+
+    async fn get_prompt(
+        &self,
+        _name: &str,
+        _arguments: Value,
+    ) -> Result<GetPromptResult, McpClientError> {
+        Err(McpClientError::NotReady)
+    }
+}
+
+fn adapter() -> McpToolAdapter {
+    McpToolAdapter::new(
+        Arc::new(MockClient),
+        "echo".to_string(),
+        "Echo the message".to_string(),
+        json!({
+            "type": "object",
+            "properties": { "message": { "type": "string" } },
+            "required": ["message"]
+        }),
+    )
+}
+
+fn args() -> McpToolArgs {
+    McpToolArgs {
+        args: json!({ "message": "hello" }),
+    }
+}
+
+fn single_call(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let adapter = adapter();
+
+    c.bench_function("single_call/adapter", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { adapter.call(args()).await.unwrap() })
+    });
+}
+
+fn concurrent_calls(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let adapter = Arc::new(adapter());
+
+    let mut group = c.benchmark_group("concurrent_calls");
+    for concurrency in [16usize, 256, 1024] {
+        group.throughput(Throughput::Elements(concurrency as u64));
+        group.bench_with_input(
+            BenchmarkId::new("adapter", concurrency),
+            &concurrency,
+            |b, &n| {
+                b.to_async(&runtime).iter(|| {
+                    let adapter = Arc::clone(&adapter);
+                    async move {
+                        let calls = (0..n).map(|_| adapter.call(args()));
+                        futures::future::join_all(calls).await
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, single_call, concurrent_calls);
+criterion_main!(benches);
@@ -6,15 +6,28 @@
 //! with Rig's agent framework. It provides implementations of Rig's `Tool`
 //! and `ToolEmbedding` traits for MCP tools.
 
+use crate::approval::{ApprovalDecision, ApprovalGate, ApprovalRequest};
+use crate::cache::ResultCache;
+use crate::cancellation::{CallGuard, InFlight, RequestCanceller};
 use crate::error::McpRigIntegrationError;
+use crate::inspection::OutputGuard;
+use crate::naming::{sanitize_tool_name, DEFAULT_MAX_TOOL_NAME_LEN};
+use crate::overrides::{self, ToolOverride};
+use crate::policy::{PolicyDecision, PolicyEngine};
+use crate::retry::RetryPolicy;
+use crate::schema::{self, SchemaProfile};
+use crate::truncation::{self, OutputBudget, OutputStash};
+use crate::validation::{self, ValidationMode};
 use mcp_client::McpClientTrait;
+use mcp_spec::protocol::CallToolResult;
 use rig::{
     completion::ToolDefinition,
     tool::{Tool, ToolEmbedding},
 };
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::sync::Arc;
+use serde_json::{Map, Value};
+use std::{sync::Arc, time::Duration};
+use sync_wrapper::SyncFuture;
 
 /// Adapter that wraps an MCP tool and implements the Rig Tool trait.
 ///
@@ -29,13 +42,53 @@ use std::sync::Arc;
 #[derive(Clone)]
 pub struct McpToolAdapter {
     /// The MCP client used to execute the tool
-    mcp_client: Arc<Box<dyn McpClientTrait>>,
-    /// The name of the MCP tool
+    mcp_client: Arc<dyn McpClientTrait>,
+    /// The name of the MCP tool, as sent to the server
     tool_name: String,
+    /// The provider-safe name advertised to the model
+    exposed_name: String,
     /// The description of the MCP tool
     tool_description: String,
     /// The JSON Schema parameters of the MCP tool
     parameters: Value,
+    /// Optional timeout for a single call to this tool
+    timeout: Option<Duration>,
+    /// Sends cancellation notifications for abandoned calls
+    canceller: Option<Arc<dyn RequestCanceller>>,
+    /// Optional retry policy for transient failures
+    retry_policy: Option<RetryPolicy>,
+    /// Whether the tool is safe to retry
+    idempotent: bool,
+    /// How arguments are checked against `parameters` before calling
+    validation: ValidationMode,
+    /// Provider rules applied to the advertised parameter schema
+    schema_profile: SchemaProfile,
+    /// The parameter schema advertised to the model
+    advertised_parameters: Value,
+    /// Lossy transformations made to produce `advertised_parameters`
+    schema_warnings: Vec<String>,
+    /// Bound or hidden arguments, always sent with these values
+    fixed_args: Map<String, Value>,
+    /// Values used for arguments the model omits
+    default_args: Map<String, Value>,
+    /// Reviews calls to sensitive tools before they run
+    approval: Option<ApprovalGate>,
+    /// Explicitly marks the tool as sensitive or not, overriding the gate's patterns
+    approval_required: Option<bool>,
+    /// Rules that must pass before a call is dispatched
+    policy: Option<Arc<PolicyEngine>>,
+    /// Scans results for prompt injection before they reach the model
+    output_guard: Option<OutputGuard>,
+    /// Limit on the size of results returned to the model
+    output_budget: Option<OutputBudget>,
+    /// Keeps the full text of truncated results for paging
+    output_stash: Option<OutputStash>,
+    /// The ID of the client this tool belongs to
+    client_id: Option<String>,
+    /// Shared cache of tool results
+    cache: Option<ResultCache>,
+    /// Whether results of this tool may be served from the cache
+    cacheable: bool,
 }
 
 impl McpToolAdapter {
@@ -55,18 +108,463 @@ impl McpToolAdapter {
     ///
     /// A new `McpToolAdapter` instance
     pub fn new(
-        mcp_client: Arc<Box<dyn McpClientTrait>>,
+        mcp_client: Arc<dyn McpClientTrait>,
         tool_name: String,
         tool_description: String,
         parameters: Value,
     ) -> Self {
         Self {
             mcp_client,
+            exposed_name: sanitize_tool_name(&tool_name, DEFAULT_MAX_TOOL_NAME_LEN),
             tool_name,
             tool_description,
+            advertised_parameters: parameters.clone(),
             parameters,
+            timeout: None,
+            canceller: None,
+            retry_policy: None,
+            idempotent: true,
+            validation: ValidationMode::default(),
+            schema_profile: SchemaProfile::default(),
+            schema_warnings: Vec::new(),
+            fixed_args: Map::new(),
+            default_args: Map::new(),
+            approval: None,
+            approval_required: None,
+            policy: None,
+            output_guard: None,
+            output_budget: None,
+            output_stash: None,
+            client_id: None,
+            cache: None,
+            cacheable: false,
         }
     }
+
+    /// Set a timeout for each call to this tool.
+    ///
+    /// The timeout applies on top of the manager-wide service timeout, so the
+    /// shorter of the two wins. A call that exceeds it is cancelled and fails
+    /// with `McpRigIntegrationError::Timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the canceller used to notify the server about abandoned calls.
+    ///
+    /// Use `McpConnectionManager::get_canceller` to obtain the canceller for
+    /// the client this tool belongs to. Without one, abandoned calls are still
+    /// stopped locally but the server is not told about it.
+    pub fn with_canceller(mut self, canceller: Arc<dyn RequestCanceller>) -> Self {
+        self.canceller = Some(canceller);
+        self
+    }
+
+    /// Set the retry policy for transient failures of this tool.
+    ///
+    /// Transport errors and timeouts are retried with backoff; tools marked
+    /// as non-idempotent with `with_idempotent(false)` are never retried.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Mark whether calling this tool more than once is safe.
+    ///
+    /// Tools are assumed to be idempotent. Pass `false` for tools with side
+    /// effects, such as sending a message or committing, to opt them out of
+    /// retries even when a retry policy is set.
+    pub fn with_idempotent(mut self, idempotent: bool) -> Self {
+        self.idempotent = idempotent;
+        self
+    }
+
+    /// Set how arguments are validated against the tool's parameter schema.
+    ///
+    /// Arguments are validated by default. `ValidationMode::Coerce` also fixes
+    /// common LLM mistakes, such as numbers sent as strings, before validating.
+    pub fn with_validation(mut self, mode: ValidationMode) -> Self {
+        self.validation = mode;
+        self
+    }
+
+    /// Set the provider rules applied to the advertised parameter schema.
+    ///
+    /// The schema sent to the model is rewritten to fit the provider's
+    /// limits, while arguments are still validated against the server's
+    /// original schema. Lossy transformations are logged and available from
+    /// `schema_warnings`.
+    pub fn with_schema_profile(mut self, profile: SchemaProfile) -> Self {
+        self.schema_profile = profile;
+        self.refresh_advertised_schema();
+        self
+    }
+
+    /// Get the lossy transformations made to the advertised parameter schema
+    pub fn schema_warnings(&self) -> &[String] {
+        &self.schema_warnings
+    }
+
+    /// Apply a description and schema override to this tool.
+    ///
+    /// The description and advertised schema are patched immediately. Hidden
+    /// parameters are removed from the advertised schema and injected with
+    /// their fixed values on every call, and defaults fill in arguments the
+    /// model leaves out.
+    pub fn with_override(mut self, tool_override: ToolOverride) -> Self {
+        self.tool_description = tool_override.apply_description(&self.tool_description);
+        self.parameters = tool_override.apply_schema(&self.parameters);
+        self.fixed_args.extend(tool_override.hidden);
+        self.default_args.extend(tool_override.defaults);
+        self.refresh_advertised_schema();
+        self
+    }
+
+    /// Bind arguments to fixed values, narrowing what the model can do.
+    ///
+    /// Bound parameters are removed from the advertised schema and merged
+    /// into the arguments of every call, replacing anything the model sends
+    /// for them. Combine with `with_exposed_name` to publish a narrowed tool
+    /// under its own name. A bound name the schema does not declare is
+    /// still sent, but is left out of argument validation.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let git_status = adapter
+    ///     .bind("repo_path", json!("/srv/repo"))
+    ///     .with_exposed_name("git_status_srv_repo");
+    /// ```
+    pub fn bind(self, parameter: impl Into<String>, value: Value) -> Self {
+        self.bind_args([(parameter.into(), value)])
+    }
+
+    /// Bind several arguments to fixed values at once.
+    ///
+    /// See `bind` for details.
+    pub fn bind_args(mut self, args: impl IntoIterator<Item = (String, Value)>) -> Self {
+        for (name, value) in args {
+            if !self.declares_parameter(&name) {
+                tracing::warn!(
+                    tool = %self.tool_name,
+                    parameter = %name,
+                    "binding an argument that is not in the tool's schema"
+                );
+            }
+            self.fixed_args.insert(name, value);
+        }
+        self.refresh_advertised_schema();
+        self
+    }
+
+    /// Check whether the parameter schema declares a property
+    fn declares_parameter(&self, name: &str) -> bool {
+        self.parameters
+            .get("properties")
+            .and_then(|p| p.get(name))
+            .is_some()
+    }
+
+    /// Get the arguments bound to fixed values, including hidden parameters
+    pub fn bound_args(&self) -> &Map<String, Value> {
+        &self.fixed_args
+    }
+
+    /// Set the policy evaluated before every call is dispatched.
+    ///
+    /// Policies are checked before approval prompts and again on arguments
+    /// edited during approval. Denied calls are not sent to the server; the
+    /// reason is returned to the model instead.
+    pub fn with_policy(mut self, policy: Arc<PolicyEngine>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Set the guard that scans results for prompt injection.
+    ///
+    /// Flagged results are annotated, redacted or withheld according to the
+    /// guard's `InspectionAction` before they are returned to the model.
+    pub fn with_output_guard(mut self, guard: OutputGuard) -> Self {
+        self.output_guard = Some(guard);
+        self
+    }
+
+    /// Limit the size of results returned to the model.
+    ///
+    /// Results whose text exceeds the budget are truncated with the budget's
+    /// `TruncationStrategy`. Apply the same budget to every adapter in the
+    /// `configure` closure of `register_mcp_tools_with` for a global limit.
+    pub fn with_output_budget(mut self, budget: OutputBudget) -> Self {
+        self.output_budget = Some(budget);
+        self
+    }
+
+    /// Keep the full text of truncated results in a stash.
+    ///
+    /// The truncation marker then includes a handle that the model can pass
+    /// to the tool from `OutputStash::reader_tool` to read the rest.
+    pub fn with_output_stash(mut self, stash: OutputStash) -> Self {
+        self.output_stash = Some(stash);
+        self
+    }
+
+    /// Set the ID of the client this tool belongs to.
+    ///
+    /// The ID scopes cached results and is returned as the `ToolEmbedding`
+    /// context. Without one, cached results are scoped to the client instance.
+    pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
+    /// Set the result cache shared with other tools.
+    ///
+    /// Calls to tools marked with `with_cacheable(true)` are served from the
+    /// cache; calls to any other tool invalidate the cached results of every
+    /// tool on the same client, since they may change what those return.
+    pub fn with_cache(mut self, cache: ResultCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Mark the tool as read-only, so its results may be cached.
+    pub fn with_cacheable(mut self, cacheable: bool) -> Self {
+        self.cacheable = cacheable;
+        self
+    }
+
+    /// Set the approval gate consulted before sensitive calls run.
+    ///
+    /// Whether this tool is sensitive is decided by the gate's name patterns
+    /// unless overridden with `with_approval_required`.
+    pub fn with_approval(mut self, gate: ApprovalGate) -> Self {
+        self.approval = Some(gate);
+        self
+    }
+
+    /// Mark the tool as needing approval, or as never needing it.
+    pub fn with_approval_required(mut self, required: bool) -> Self {
+        self.approval_required = Some(required);
+        self
+    }
+
+    /// Set the name advertised to the model.
+    ///
+    /// By default the MCP tool name is sanitized with `sanitize_tool_name`.
+    /// Calls always use the original MCP name regardless of this setting.
+    pub fn with_exposed_name(mut self, name: impl Into<String>) -> Self {
+        self.exposed_name = name.into();
+        self
+    }
+
+    /// Get the name of the MCP tool
+    pub fn tool_name(&self) -> &str {
+        &self.tool_name
+    }
+
+    /// Get the name advertised to the model
+    pub fn exposed_name(&self) -> &str {
+        &self.exposed_name
+    }
+
+    /// Get the ID of the client this tool belongs to
+    pub fn client_id(&self) -> Option<&str> {
+        self.client_id.as_deref()
+    }
+
+    /// Recompute the advertised parameter schema from the server's schema.
+    fn refresh_advertised_schema(&mut self) {
+        let visible = overrides::hide_parameters(&self.parameters, self.fixed_args.keys());
+        let normalized = schema::normalize_schema(&visible, self.schema_profile);
+        for warning in &normalized.lossy {
+            tracing::warn!(
+                tool = %self.tool_name,
+                profile = ?self.schema_profile,
+                "lossy schema transformation: {}",
+                warning
+            );
+        }
+        self.advertised_parameters = normalized.schema;
+        self.schema_warnings = normalized.lossy;
+    }
+
+    /// Build the arguments sent to the server from the model's arguments.
+    ///
+    /// Fixed and default values are merged in, then the result is coerced
+    /// and validated against the parameter schema if enabled. Invalid
+    /// arguments return the message listing every failing path.
+    fn prepare_args(&self, mut args: Value) -> Result<Value, Value> {
+        if self.schema_profile == SchemaProfile::OpenAiStrict {
+            schema::strip_null_placeholders(&self.parameters, &mut args);
+        }
+
+        for name in overrides::merge_fixed_args(&mut args, &self.fixed_args) {
+            tracing::warn!(
+                tool = %self.tool_name,
+                parameter = %name,
+                "model tried to set a fixed argument; the fixed value was used"
+            );
+        }
+        overrides::fill_defaults(&mut args, &self.default_args);
+
+        // Fixed values the schema does not declare would fail a closed schema
+        // on every call, so they are set aside until validation is done
+        let mut undeclared = Map::new();
+        if let Value::Object(object) = &mut args {
+            for name in self.fixed_args.keys() {
+                if !self.declares_parameter(name) {
+                    if let Some(value) = object.remove(name) {
+                        undeclared.insert(name.clone(), value);
+                    }
+                }
+            }
+        }
+
+        if self.validation == ValidationMode::Coerce {
+            for change in validation::coerce(&self.parameters, &mut args) {
+                tracing::debug!(tool = %self.tool_name, change = %change, "coerced tool argument");
+            }
+        }
+
+        if self.validation != ValidationMode::Disabled {
+            let issues = validation::validate(&self.parameters, &args);
+            if !issues.is_empty() {
+                return Err(Value::String(validation::describe_issues(
+                    &self.tool_name,
+                    &issues,
+                )));
+            }
+        }
+
+        if let Value::Object(object) = &mut args {
+            object.extend(undeclared);
+        }
+        Ok(args)
+    }
+
+    /// Check the call against the policy, returning the denial message if any.
+    fn check_policy(&self, args: &Value) -> Option<Value> {
+        match self.policy.as_ref()?.evaluate(&self.tool_name, args) {
+            PolicyDecision::Allow => None,
+            PolicyDecision::Deny { reason, .. } => Some(Value::String(format!(
+                "The call to '{}' was blocked by policy: {}",
+                self.exposed_name, reason
+            ))),
+        }
+    }
+
+    /// Get the approval gate if this tool's calls need review.
+    fn approval_gate(&self) -> Option<&ApprovalGate> {
+        let gate = self.approval.as_ref()?;
+        self.approval_required
+            .unwrap_or_else(|| gate.applies_to(&self.tool_name))
+            .then_some(gate)
+    }
+
+    /// Call the tool through the result cache, if one is set.
+    async fn call_with_cache(&self, args: Value) -> Result<CallToolResult, McpRigIntegrationError> {
+        let Some(cache) = &self.cache else {
+            return self.call_with_retry(args).await;
+        };
+        // Scope entries to the client instance when no ID was given
+        let client = self
+            .client_id
+            .clone()
+            .unwrap_or_else(|| format!("{:p}", Arc::as_ptr(&self.mcp_client)));
+
+        if !self.cacheable {
+            let result = self.call_with_retry(args).await;
+            cache.invalidate_client(&client);
+            return result;
+        }
+
+        if let Some(hit) = cache.get(&client, &self.tool_name, &args) {
+            tracing::debug!(tool = %self.tool_name, client = %client, "serving tool result from cache");
+            return Ok(hit);
+        }
+
+        // A mutating call finishing meanwhile makes this result stale
+        let generation = cache.generation(&client);
+        let result = self.call_with_retry(args.clone()).await?;
+        if !result.is_error.unwrap_or(false) {
+            cache.insert_if_current(
+                &client,
+                &self.tool_name,
+                &args,
+                result.content.clone(),
+                generation,
+            );
+        }
+        Ok(result)
+    }
+
+    /// Call the tool, retrying transient failures according to the retry policy.
+    async fn call_with_retry(&self, args: Value) -> Result<CallToolResult, McpRigIntegrationError> {
+        let policy = match &self.retry_policy {
+            Some(policy) if self.idempotent => policy,
+            _ => return self.call_once(args).await,
+        };
+
+        let mut attempt = 1;
+        loop {
+            match self.call_once(args.clone()).await {
+                Err(e) if e.is_transient() && attempt < policy.max_attempts => {
+                    let delay = policy.backoff(attempt);
+                    tracing::warn!(
+                        tool = %self.tool_name,
+                        attempt,
+                        max_attempts = policy.max_attempts,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %e,
+                        "transient MCP tool failure, retrying"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => {
+                    if attempt > 1 {
+                        tracing::info!(
+                            tool = %self.tool_name,
+                            attempts = attempt,
+                            success = result.is_ok(),
+                            "MCP tool call finished after retries"
+                        );
+                    }
+                    return result;
+                }
+            }
+        }
+    }
+
+    /// Make a single call to the tool, honoring the timeout and cancellation.
+    async fn call_once(&self, args: Value) -> Result<CallToolResult, McpRigIntegrationError> {
+        // Track the request IDs sent by this call so they can be cancelled.
+        // The guard notifies the server if this future is dropped or times
+        // out before the call completes.
+        let in_flight = InFlight::default();
+        let mut guard = CallGuard::new(in_flight.clone(), self.canceller.clone());
+
+        // Rig requires the call future to be Sync; the client's is not
+        let call =
+            SyncFuture::new(in_flight.scope(self.mcp_client.call_tool(&self.tool_name, args)));
+        let result = match self.timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, call).await {
+                Ok(result) => result,
+                Err(_) => {
+                    guard.cancel("the request timed out");
+                    return Err(McpRigIntegrationError::Timeout(format!(
+                        "tool '{}' did not respond within {:?}",
+                        self.tool_name, timeout
+                    )));
+                }
+            },
+            None => call.await,
+        };
+        guard.disarm();
+
+        result.map_err(McpRigIntegrationError::from)
+    }
 }
 
 /// Arguments for an MCP tool call.
@@ -100,7 +598,7 @@ pub struct McpToolState {
 }
 
 // Type alias for ClientId to use as Context
-// This is serializable, unlike Arc<Box<dyn McpClientTrait>>
+// This is serializable, unlike Arc<dyn McpClientTrait>
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ClientId(pub String);
 
@@ -118,40 +616,62 @@ impl Tool for McpToolAdapter {
     type Output = Value;
 
     async fn definition(&self, _prompt: String) -> ToolDefinition {
-        // The key is that we use the actual MCP tool name here
+        // The model sees the sanitized name; `call` maps back to the MCP name
         ToolDefinition {
-            name: self.tool_name.clone(), // This is the MCP tool name
+            name: self.exposed_name.clone(),
             description: self.tool_description.clone(),
-            parameters: self.parameters.clone(),
+            parameters: self.advertised_parameters.clone(),
         }
     }
 
     // Override the name() method to return the dynamic tool name
     fn name(&self) -> String {
-        self.tool_name.clone()
+        self.exposed_name.clone()
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        // Clone everything we need to move into the async block
-        let mcp_client = Arc::clone(&self.mcp_client);
-        let tool_name = self.tool_name.clone();
-        let args_value = args.args.clone();
-
-        // Use tokio::spawn but ensure the future is properly constructed
-        // We'll use a pattern that ensures the future is properly constrained
-        let result = tokio::task::spawn(async move {
-            // This code runs in a separate task
-            match mcp_client.call_tool(&tool_name, args_value).await {
-                Ok(result) => Ok(result),
-                Err(e) => Err(McpRigIntegrationError::McpError(e.to_string())),
+        // Validation failures are returned as output so the model can fix them
+        let mut args = match self.prepare_args(args.args) {
+            Ok(args) => args,
+            Err(issues) => return Ok(issues),
+        };
+
+        // Policy denials are returned as output so the model can adjust
+        if let Some(denial) = self.check_policy(&args) {
+            return Ok(denial);
+        }
+
+        // Sensitive tools need approval before they run
+        if let Some(gate) = self.approval_gate() {
+            let request = ApprovalRequest {
+                tool_name: self.tool_name.clone(),
+                exposed_name: self.exposed_name.clone(),
+                description: self.tool_description.clone(),
+                arguments: args.clone(),
+            };
+            // Rig requires the call future to be Sync; the handler's is not
+            match SyncFuture::new(gate.review(&request)).await {
+                ApprovalDecision::Approve => {}
+                ApprovalDecision::Deny { reason } => {
+                    // Returned as output so the model can explain or adjust
+                    return Ok(Value::String(format!(
+                        "The call to '{}' was not approved: {}",
+                        self.exposed_name, reason
+                    )));
+                }
+                ApprovalDecision::Edit { arguments } => {
+                    args = match self.prepare_args(arguments) {
+                        Ok(args) => args,
+                        Err(issues) => return Ok(issues),
+                    };
+                    if let Some(denial) = self.check_policy(&args) {
+                        return Ok(denial);
+                    }
+                }
             }
-        })
-        .await // Wait for the spawned task to complete
-        .map_err(|e| McpRigIntegrationError::McpError(format!("Task join error: {}", e)))?; // Handle JoinError
+        }
 
-        // Now we've properly awaited the spawned task and have our result
-        // The `result` here is already a Result<CallToolResult, McpRigIntegrationError>
-        let tool_result = result?;
+        let mut tool_result = self.call_with_cache(args).await?;
 
         // Handle errors from the tool execution
         if tool_result.is_error.unwrap_or(false) {
@@ -161,6 +681,22 @@ impl Tool for McpToolAdapter {
             )));
         }
 
+        // Withheld results are returned as output so the model can explain
+        if let Some(guard) = &self.output_guard {
+            if let Some(message) = guard.apply(&self.exposed_name, &mut tool_result.content) {
+                return Ok(Value::String(message));
+            }
+        }
+
+        if let Some(budget) = &self.output_budget {
+            truncation::apply_budget(
+                &self.exposed_name,
+                budget,
+                self.output_stash.as_ref(),
+                &mut tool_result.content,
+            );
+        }
+
         Ok(serde_json::to_value(tool_result.content)?)
     }
 }
@@ -186,7 +722,7 @@ impl ToolEmbedding for McpToolAdapter {
             // The main description
             self.tool_description.clone(),
             // Include the tool name to help with direct name references
-            format!("Tool name: {}", self.tool_name),
+            format!("Tool name: {}", self.exposed_name),
             // Restate the capability to give the embedding more context
             format!("Tool capability: {}", self.tool_description),
         ]
@@ -194,8 +730,7 @@ impl ToolEmbedding for McpToolAdapter {
 
     /// Provides the context needed to recreate this tool.
     fn context(&self) -> Self::Context {
-        // In a real implementation, this would return the client ID
-        // that can be used to look up the actual client.
-        ClientId("client-id".to_string())
+        // The client ID can be used to look up the client in a manager
+        ClientId(self.client_id.clone().unwrap_or_default())
     }
 }
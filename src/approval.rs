@@ -0,0 +1,238 @@
+// src/approval.rs
+
+//! Human-in-the-loop approval for sensitive tool calls.
+//!
+//! An `ApprovalHandler` is consulted by `McpToolAdapter::call` before a
+//! sensitive tool runs. It can approve the call, deny it with a reason that
+//! is returned to the model, or edit the arguments. Which tools count as
+//! sensitive is decided by an `ApprovalGate` from tool name patterns, or per
+//! tool with `McpToolAdapter::with_approval_required` and the
+//! `requireApproval` config setting.
+//!
+//! Two handlers are included: `StdinApprover` prompts on the terminal, and
+//! `AutoDenyApprover` rejects everything, which is useful in tests.
+
+use crate::glob::glob_match;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Stdin};
+use tokio::sync::Mutex;
+
+/// A tool call waiting for approval.
+#[derive(Debug, Clone)]
+pub struct ApprovalRequest {
+    /// The MCP name of the tool
+    pub tool_name: String,
+    /// The name the model used for the tool
+    pub exposed_name: String,
+    /// The tool's description
+    pub description: String,
+    /// The arguments the tool will be called with
+    pub arguments: Value,
+}
+
+/// The outcome of an approval review.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApprovalDecision {
+    /// Run the call as requested
+    Approve,
+    /// Don't run the call; the reason is returned to the model
+    Deny { reason: String },
+    /// Run the call with different arguments
+    Edit { arguments: Value },
+}
+
+/// Reviews sensitive tool calls before they run.
+#[async_trait::async_trait]
+pub trait ApprovalHandler: Send + Sync {
+    /// Decide whether the call may proceed.
+    async fn review(&self, request: &ApprovalRequest) -> ApprovalDecision;
+}
+
+/// Selects which tools need approval and who approves them.
+///
+/// Tools are selected by name only. mcp-spec 0.1.0 does not expose tool
+/// annotations, so the server's `destructiveHint` can't be used yet.
+///
+/// # Example
+///
+/// ```rust
+/// use mcp_rig::{ApprovalGate, AutoDenyApprover};
+/// use std::sync::Arc;
+///
+/// let gate = ApprovalGate::new(Arc::new(AutoDenyApprover::default()))
+///     .for_tools(["write_file", "edit_file", "git_*"]);
+/// assert!(gate.applies_to("git_commit"));
+/// assert!(!gate.applies_to("read_file"));
+/// ```
+#[derive(Clone)]
+pub struct ApprovalGate {
+    /// The handler that reviews calls
+    handler: Arc<dyn ApprovalHandler>,
+    /// Tool name patterns that need approval; empty means none by pattern
+    patterns: Vec<String>,
+}
+
+impl ApprovalGate {
+    /// Create a gate that only applies to tools explicitly marked as sensitive.
+    pub fn new(handler: Arc<dyn ApprovalHandler>) -> Self {
+        Self {
+            handler,
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Require approval for tools whose MCP name matches any of the patterns.
+    ///
+    /// Patterns support `*` and `?` wildcards.
+    pub fn for_tools<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.patterns.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Check whether a tool needs approval based on the gate's patterns
+    pub fn applies_to(&self, tool_name: &str) -> bool {
+        self.patterns.iter().any(|p| glob_match(p, tool_name))
+    }
+
+    /// Ask the handler to review a call
+    pub async fn review(&self, request: &ApprovalRequest) -> ApprovalDecision {
+        let decision = self.handler.review(request).await;
+        tracing::info!(
+            tool = %request.tool_name,
+            decision = ?decision,
+            "tool call approval decision"
+        );
+        decision
+    }
+}
+
+/// Approval handler that denies every call.
+#[derive(Debug, Clone)]
+pub struct AutoDenyApprover {
+    /// The reason returned to the model
+    pub reason: String,
+}
+
+impl Default for AutoDenyApprover {
+    fn default() -> Self {
+        Self {
+            reason: "tool calls that need approval are disabled".to_string(),
+        }
+    }
+}
+
+impl AutoDenyApprover {
+    /// Create a handler that denies every call with the given reason.
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ApprovalHandler for AutoDenyApprover {
+    async fn review(&self, _request: &ApprovalRequest) -> ApprovalDecision {
+        ApprovalDecision::Deny {
+            reason: self.reason.clone(),
+        }
+    }
+}
+
+/// Approval handler that prompts on the terminal.
+///
+/// The call is shown on stderr and the answer is read from stdin:
+/// `y` approves, `n` followed by an optional reason denies, and `e` asks for
+/// replacement arguments as a single line of JSON. Prompts from concurrent
+/// calls are shown one at a time.
+///
+/// A program that reads stdin itself, such as a chat loop, must read through
+/// the same reader with `with_reader`. Otherwise each buffers input meant for
+/// the other, and typed-ahead or piped answers are lost.
+pub struct StdinApprover {
+    /// Shared stdin reader; holding the lock serializes prompts
+    stdin: Arc<Mutex<BufReader<Stdin>>>,
+}
+
+impl Default for StdinApprover {
+    fn default() -> Self {
+        Self::with_reader(Arc::new(Mutex::new(BufReader::new(tokio::io::stdin()))))
+    }
+}
+
+impl StdinApprover {
+    /// Create a terminal approval handler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a terminal approval handler that reads answers from a shared reader.
+    pub fn with_reader(stdin: Arc<Mutex<BufReader<Stdin>>>) -> Self {
+        Self { stdin }
+    }
+}
+
+async fn prompt(stdin: &mut BufReader<Stdin>, message: &str) -> Option<String> {
+    let mut stderr = tokio::io::stderr();
+    stderr.write_all(message.as_bytes()).await.ok()?;
+    stderr.flush().await.ok()?;
+
+    let mut line = String::new();
+    match stdin.read_line(&mut line).await {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim().to_string()),
+    }
+}
+
+#[async_trait::async_trait]
+impl ApprovalHandler for StdinApprover {
+    async fn review(&self, request: &ApprovalRequest) -> ApprovalDecision {
+        let mut stdin = self.stdin.lock().await;
+
+        let arguments = serde_json::to_string_pretty(&request.arguments)
+            .unwrap_or_else(|_| request.arguments.to_string());
+        let header = format!(
+            "\nThe agent wants to call '{}' with:\n{}\n",
+            request.tool_name, arguments
+        );
+
+        let mut message = format!("{}Approve? [y]es / [n]o [reason] / [e]dit: ", header);
+        loop {
+            let Some(answer) = prompt(&mut stdin, &message).await else {
+                return ApprovalDecision::Deny {
+                    reason: "no approval input available".to_string(),
+                };
+            };
+
+            let (command, rest) = answer.split_once(' ').unwrap_or((answer.as_str(), ""));
+            match command.to_ascii_lowercase().as_str() {
+                "y" | "yes" => return ApprovalDecision::Approve,
+                "n" | "no" => {
+                    let reason = if rest.trim().is_empty() {
+                        "the user declined this tool call".to_string()
+                    } else {
+                        rest.trim().to_string()
+                    };
+                    return ApprovalDecision::Deny { reason };
+                }
+                "e" | "edit" => {
+                    let Some(edited) =
+                        prompt(&mut stdin, "New arguments (JSON on one line): ").await
+                    else {
+                        continue;
+                    };
+                    match serde_json::from_str(&edited) {
+                        Ok(arguments) => return ApprovalDecision::Edit { arguments },
+                        Err(e) => message = format!("Invalid JSON ({}). Approve? [y/n/e]: ", e),
+                    }
+                }
+                _ => message = "Please answer y, n or e: ".to_string(),
+            }
+        }
+    }
+}
@@ -0,0 +1,720 @@
+// src/auth.rs
+
+//! OAuth 2.1 authorization for remote MCP servers.
+//!
+//! An `AuthProvider` supplies the `Authorization` header for a server.
+//! Register one with `McpConnectionManager::set_auth_provider` before adding
+//! the server, or pass it to `StreamableHttpTransport::with_auth` or
+//! `WebSocketTransport::with_auth` directly. The Streamable HTTP transport
+//! asks for the header on every request, so tokens are refreshed while the
+//! connection is open; the WebSocket transport asks on every (re)connect.
+//!
+//! `OAuthProvider` implements two grants:
+//!
+//! - client credentials, for services authenticating as themselves
+//! - authorization code with PKCE, for users; the login page is opened
+//!   through a callback and the code is received on a local redirect
+//!   listener at `http://127.0.0.1:<port>/callback`
+//!
+//! Tokens are kept in a `TokenStore` so they survive restarts, and are
+//! refreshed shortly before they expire. When a server rejects a token, the
+//! transports call `AuthProvider::invalidate` and retry once.
+
+use crate::error::McpRigIntegrationError;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use url::Url;
+
+/// Supplies the `Authorization` header for requests to a server.
+#[async_trait::async_trait]
+pub trait AuthProvider: Send + Sync {
+    /// Get the value of the `Authorization` header, obtaining or refreshing a token if needed.
+    async fn authorization(&self) -> Result<String, McpRigIntegrationError>;
+
+    /// Discard the current access token after the server rejected it.
+    async fn invalidate(&self) {}
+}
+
+/// A static header value, for servers with long-lived API tokens.
+pub struct StaticAuth(pub String);
+
+#[async_trait::async_trait]
+impl AuthProvider for StaticAuth {
+    async fn authorization(&self) -> Result<String, McpRigIntegrationError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// An OAuth access token and what is needed to renew it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthToken {
+    /// The access token sent to the server
+    pub access_token: String,
+    /// The token type, normally `Bearer`
+    pub token_type: String,
+    /// Token used to obtain a new access token without the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// When the access token expires, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// The scopes granted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+impl OAuthToken {
+    /// Check whether the token expires within `margin` from now
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        match self.expires_at {
+            Some(expires_at) => now_secs() + margin.as_secs() >= expires_at,
+            None => false,
+        }
+    }
+
+    /// Get the value of the `Authorization` header
+    pub fn header_value(&self) -> String {
+        // Servers expect the canonical capitalization even if the token type is `bearer`
+        let token_type = if self.token_type.eq_ignore_ascii_case("bearer") {
+            "Bearer"
+        } else {
+            &self.token_type
+        };
+        format!("{} {}", token_type, self.access_token)
+    }
+}
+
+/// Storage for tokens between runs.
+///
+/// Tokens are stored under a key chosen by the provider, normally the
+/// server ID.
+#[async_trait::async_trait]
+pub trait TokenStore: Send + Sync {
+    /// Load the token stored under `key`
+    async fn load(&self, key: &str) -> Result<Option<OAuthToken>, McpRigIntegrationError>;
+
+    /// Store a token under `key`, replacing any previous one
+    async fn save(&self, key: &str, token: &OAuthToken) -> Result<(), McpRigIntegrationError>;
+
+    /// Remove the token stored under `key`
+    async fn remove(&self, key: &str) -> Result<(), McpRigIntegrationError>;
+}
+
+/// Keeps tokens in memory only.
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore {
+    tokens: Mutex<HashMap<String, OAuthToken>>,
+}
+
+#[async_trait::async_trait]
+impl TokenStore for MemoryTokenStore {
+    async fn load(&self, key: &str) -> Result<Option<OAuthToken>, McpRigIntegrationError> {
+        Ok(self.tokens.lock().unwrap().get(key).cloned())
+    }
+
+    async fn save(&self, key: &str, token: &OAuthToken) -> Result<(), McpRigIntegrationError> {
+        self.tokens
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), token.clone());
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), McpRigIntegrationError> {
+        self.tokens.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+/// Keeps tokens in a JSON file, readable only by the current user on Unix.
+#[derive(Debug)]
+pub struct FileTokenStore {
+    path: PathBuf,
+    /// Serializes read-modify-write cycles within this process
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl FileTokenStore {
+    /// Create a store backed by the file at `path`, which is created on first save.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn read(&self) -> Result<HashMap<String, OAuthToken>, McpRigIntegrationError> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(McpRigIntegrationError::AuthError(format!(
+                "failed to read token store {}: {}",
+                self.path.display(),
+                e
+            ))),
+        }
+    }
+
+    fn write(&self, tokens: &HashMap<String, OAuthToken>) -> Result<(), McpRigIntegrationError> {
+        let contents = serde_json::to_string_pretty(tokens)?;
+        write_private(&self.path, contents.as_bytes()).map_err(|e| {
+            McpRigIntegrationError::AuthError(format!(
+                "failed to write token store {}: {}",
+                self.path.display(),
+                e
+            ))
+        })
+    }
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::{io::Write, os::unix::fs::OpenOptionsExt};
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(contents)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    std::fs::write(path, contents)
+}
+
+#[async_trait::async_trait]
+impl TokenStore for FileTokenStore {
+    async fn load(&self, key: &str) -> Result<Option<OAuthToken>, McpRigIntegrationError> {
+        let _guard = self.lock.lock().await;
+        Ok(self.read()?.remove(key))
+    }
+
+    async fn save(&self, key: &str, token: &OAuthToken) -> Result<(), McpRigIntegrationError> {
+        let _guard = self.lock.lock().await;
+        let mut tokens = self.read()?;
+        tokens.insert(key.to_string(), token.clone());
+        self.write(&tokens)
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), McpRigIntegrationError> {
+        let _guard = self.lock.lock().await;
+        let mut tokens = self.read()?;
+        if tokens.remove(key).is_some() {
+            self.write(&tokens)?;
+        }
+        Ok(())
+    }
+}
+
+/// Opens the authorization page for the user.
+pub type BrowserOpener = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// How an `OAuthProvider` obtains new tokens.
+#[derive(Clone)]
+enum Grant {
+    ClientCredentials {
+        client_secret: String,
+    },
+    AuthorizationCode {
+        authorization_url: String,
+        client_secret: Option<String>,
+        redirect_port: u16,
+        open_browser: BrowserOpener,
+        login_timeout: Duration,
+    },
+}
+
+/// Obtains, refreshes and stores OAuth tokens for one server.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let provider = OAuthProvider::authorization_code(
+///     "https://auth.example.com/authorize",
+///     "https://auth.example.com/token",
+///     "mcp-rig",
+/// )
+/// .with_scopes(["tools:read", "tools:call"])
+/// .with_store(Arc::new(FileTokenStore::new("tokens.json")), "remote");
+///
+/// manager.set_auth_provider("remote", Arc::new(provider));
+/// manager.add_http_client("remote".to_string(), url, HashMap::new(), client_info).await?;
+/// ```
+pub struct OAuthProvider {
+    /// The token endpoint
+    token_url: String,
+    /// The client ID registered with the authorization server
+    client_id: String,
+    /// How new tokens are obtained
+    grant: Grant,
+    /// Scopes to request
+    scopes: Vec<String>,
+    /// Resource indicator identifying the MCP server (RFC 8707)
+    resource: Option<String>,
+    /// Where tokens are kept between runs
+    store: Arc<dyn TokenStore>,
+    /// Key of this provider's token in the store
+    key: String,
+    /// How long before expiry a token is refreshed
+    refresh_margin: Duration,
+    http: reqwest::Client,
+    /// The current token; the lock is held while a token is being obtained
+    current: tokio::sync::Mutex<Option<OAuthToken>>,
+}
+
+impl OAuthProvider {
+    /// Create a provider using the client credentials grant.
+    pub fn client_credentials(
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self::new(
+            token_url.into(),
+            client_id.into(),
+            Grant::ClientCredentials {
+                client_secret: client_secret.into(),
+            },
+        )
+    }
+
+    /// Create a provider using the authorization code grant with PKCE.
+    ///
+    /// By default the authorization URL is printed to stderr for the user to
+    /// open; use `with_browser` to open it another way.
+    pub fn authorization_code(
+        authorization_url: impl Into<String>,
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+    ) -> Self {
+        Self::new(
+            token_url.into(),
+            client_id.into(),
+            Grant::AuthorizationCode {
+                authorization_url: authorization_url.into(),
+                client_secret: None,
+                redirect_port: 0,
+                open_browser: Arc::new(|url| {
+                    eprintln!("Open this URL in a browser to authorize access:\n{}", url)
+                }),
+                login_timeout: Duration::from_secs(300),
+            },
+        )
+    }
+
+    fn new(token_url: String, client_id: String, grant: Grant) -> Self {
+        Self {
+            key: client_id.clone(),
+            token_url,
+            client_id,
+            grant,
+            scopes: Vec::new(),
+            resource: None,
+            store: Arc::new(MemoryTokenStore::default()),
+            refresh_margin: Duration::from_secs(60),
+            http: reqwest::Client::new(),
+            current: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Set the scopes to request.
+    pub fn with_scopes<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the resource indicator sent with token requests, normally the server URL.
+    pub fn with_resource(mut self, resource: impl Into<String>) -> Self {
+        self.resource = Some(resource.into());
+        self
+    }
+
+    /// Keep tokens in `store` under `key` instead of in memory.
+    pub fn with_store(mut self, store: Arc<dyn TokenStore>, key: impl Into<String>) -> Self {
+        self.store = store;
+        self.key = key.into();
+        self
+    }
+
+    /// Set how long before expiry a token is refreshed.
+    pub fn with_refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    /// Set the client secret of a confidential client using the authorization code grant.
+    pub fn with_client_secret(mut self, secret: impl Into<String>) -> Self {
+        match &mut self.grant {
+            Grant::ClientCredentials { client_secret } => *client_secret = secret.into(),
+            Grant::AuthorizationCode { client_secret, .. } => *client_secret = Some(secret.into()),
+        }
+        self
+    }
+
+    /// Set the port of the local redirect listener; 0 picks a free port.
+    ///
+    /// Authorization servers that require pre-registered redirect URIs need
+    /// a fixed port. Has no effect on the client credentials grant.
+    pub fn with_redirect_port(mut self, port: u16) -> Self {
+        if let Grant::AuthorizationCode { redirect_port, .. } = &mut self.grant {
+            *redirect_port = port;
+        }
+        self
+    }
+
+    /// Set how the authorization page is opened for the user.
+    pub fn with_browser(mut self, opener: BrowserOpener) -> Self {
+        if let Grant::AuthorizationCode { open_browser, .. } = &mut self.grant {
+            *open_browser = opener;
+        }
+        self
+    }
+
+    /// Set how long to wait for the user to complete the login.
+    pub fn with_login_timeout(mut self, timeout: Duration) -> Self {
+        if let Grant::AuthorizationCode { login_timeout, .. } = &mut self.grant {
+            *login_timeout = timeout;
+        }
+        self
+    }
+
+    /// Get a valid token, obtaining or refreshing one if needed
+    pub async fn token(&self) -> Result<OAuthToken, McpRigIntegrationError> {
+        let mut current = self.current.lock().await;
+        if current.is_none() {
+            *current = self.store.load(&self.key).await?;
+        }
+        if let Some(token) = current.as_ref() {
+            if !token.expires_within(self.refresh_margin) {
+                return Ok(token.clone());
+            }
+        }
+
+        let previous = current.take();
+        let refreshed = match previous.as_ref().and_then(|t| t.refresh_token.clone()) {
+            Some(refresh_token) => match self.refresh(&refresh_token).await {
+                Ok(token) => Some(token),
+                Err(e) => {
+                    tracing::warn!(key = %self.key, error = %e, "token refresh failed, authorizing again");
+                    None
+                }
+            },
+            None => None,
+        };
+        let token = match refreshed {
+            Some(token) => token,
+            None => self.obtain().await?,
+        };
+
+        self.store.save(&self.key, &token).await?;
+        *current = Some(token.clone());
+        Ok(token)
+    }
+
+    /// Exchange a refresh token for a new access token.
+    async fn refresh(&self, refresh_token: &str) -> Result<OAuthToken, McpRigIntegrationError> {
+        tracing::debug!(key = %self.key, "refreshing access token");
+        let mut form = vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token.to_string()),
+        ];
+        self.add_client_auth(&mut form);
+        let mut token = self.request_token(form).await?;
+        // Servers that do not rotate refresh tokens omit them from the response
+        if token.refresh_token.is_none() {
+            token.refresh_token = Some(refresh_token.to_string());
+        }
+        Ok(token)
+    }
+
+    /// Obtain a new token with the configured grant.
+    async fn obtain(&self) -> Result<OAuthToken, McpRigIntegrationError> {
+        match &self.grant {
+            Grant::ClientCredentials { .. } => {
+                tracing::debug!(key = %self.key, "requesting token with client credentials");
+                let mut form = vec![("grant_type", "client_credentials".to_string())];
+                self.add_client_auth(&mut form);
+                self.add_scope(&mut form);
+                self.request_token(form).await
+            }
+            Grant::AuthorizationCode {
+                authorization_url,
+                redirect_port,
+                open_browser,
+                login_timeout,
+                ..
+            } => {
+                let listener = TcpListener::bind(("127.0.0.1", *redirect_port))
+                    .await
+                    .map_err(|e| auth_error("failed to start the redirect listener", e))?;
+                let port = listener
+                    .local_addr()
+                    .map_err(|e| auth_error("failed to start the redirect listener", e))?
+                    .port();
+                let redirect_uri = format!("http://127.0.0.1:{}/callback", port);
+
+                let verifier = random_string(64);
+                let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+                let state = random_string(32);
+
+                let mut url = Url::parse(authorization_url)
+                    .map_err(|e| auth_error("invalid authorization URL", e))?;
+                {
+                    let mut query = url.query_pairs_mut();
+                    query
+                        .append_pair("response_type", "code")
+                        .append_pair("client_id", &self.client_id)
+                        .append_pair("redirect_uri", &redirect_uri)
+                        .append_pair("code_challenge", &challenge)
+                        .append_pair("code_challenge_method", "S256")
+                        .append_pair("state", &state);
+                    if !self.scopes.is_empty() {
+                        query.append_pair("scope", &self.scopes.join(" "));
+                    }
+                    if let Some(resource) = &self.resource {
+                        query.append_pair("resource", resource);
+                    }
+                }
+
+                open_browser(url.as_str());
+                let code = tokio::time::timeout(*login_timeout, receive_code(&listener, &state))
+                    .await
+                    .map_err(|_| {
+                        McpRigIntegrationError::AuthError(
+                            "timed out waiting for the user to authorize".to_string(),
+                        )
+                    })??;
+
+                let mut form = vec![
+                    ("grant_type", "authorization_code".to_string()),
+                    ("code", code),
+                    ("redirect_uri", redirect_uri),
+                    ("code_verifier", verifier),
+                ];
+                self.add_client_auth(&mut form);
+                self.request_token(form).await
+            }
+        }
+    }
+
+    fn add_client_auth(&self, form: &mut Vec<(&'static str, String)>) {
+        form.push(("client_id", self.client_id.clone()));
+        let secret = match &self.grant {
+            Grant::ClientCredentials { client_secret } => Some(client_secret),
+            Grant::AuthorizationCode { client_secret, .. } => client_secret.as_ref(),
+        };
+        if let Some(secret) = secret {
+            form.push(("client_secret", secret.clone()));
+        }
+        if let Some(resource) = &self.resource {
+            form.push(("resource", resource.clone()));
+        }
+    }
+
+    fn add_scope(&self, form: &mut Vec<(&'static str, String)>) {
+        if !self.scopes.is_empty() {
+            form.push(("scope", self.scopes.join(" ")));
+        }
+    }
+
+    /// POST a form to the token endpoint and parse the token.
+    async fn request_token(
+        &self,
+        form: Vec<(&'static str, String)>,
+    ) -> Result<OAuthToken, McpRigIntegrationError> {
+        let response = self
+            .http
+            .post(&self.token_url)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| auth_error("token request failed", e))?;
+        let status = response.status();
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| auth_error("invalid token response", e))?;
+
+        if !status.is_success() {
+            let error = body["error"].as_str().unwrap_or("unknown_error");
+            let description = body["error_description"].as_str().unwrap_or_default();
+            return Err(McpRigIntegrationError::AuthError(format!(
+                "token endpoint returned {}: {} {}",
+                status, error, description
+            )));
+        }
+
+        let response: TokenResponse = serde_json::from_value(body)?;
+        Ok(OAuthToken {
+            access_token: response.access_token,
+            token_type: response.token_type.unwrap_or_else(|| "Bearer".to_string()),
+            refresh_token: response.refresh_token,
+            expires_at: response.expires_in.map(|secs| now_secs() + secs),
+            scope: response.scope,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthProvider for OAuthProvider {
+    async fn authorization(&self) -> Result<String, McpRigIntegrationError> {
+        Ok(self.token().await?.header_value())
+    }
+
+    async fn invalidate(&self) {
+        // Keep the refresh token so the next request can renew without the user
+        let mut current = self.current.lock().await;
+        if let Some(token) = current.as_mut() {
+            token.expires_at = Some(0);
+        }
+    }
+}
+
+/// A successful response from the token endpoint.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    token_type: Option<String>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<u64>,
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+/// Wait for the browser to be redirected back with an authorization code.
+async fn receive_code(
+    listener: &TcpListener,
+    state: &str,
+) -> Result<String, McpRigIntegrationError> {
+    loop {
+        let (mut stream, _) = listener
+            .accept()
+            .await
+            .map_err(|e| auth_error("redirect listener failed", e))?;
+        let Some(target) = read_request_target(&mut stream).await else {
+            continue;
+        };
+        let Some(query) = target.strip_prefix("/callback?") else {
+            // Browsers also ask for things like /favicon.ico
+            respond(&mut stream, "404 Not Found", "Not found").await;
+            continue;
+        };
+
+        let params: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+        if params.get("state").map(String::as_str) != Some(state) {
+            respond(
+                &mut stream,
+                "400 Bad Request",
+                "Authorization failed: state mismatch.",
+            )
+            .await;
+            return Err(McpRigIntegrationError::AuthError(
+                "authorization response had the wrong state".to_string(),
+            ));
+        }
+        if let Some(error) = params.get("error") {
+            respond(&mut stream, "400 Bad Request", "Authorization was denied.").await;
+            let description = params.get("error_description").cloned().unwrap_or_default();
+            return Err(McpRigIntegrationError::AuthError(format!(
+                "authorization denied: {} {}",
+                error, description
+            )));
+        }
+        let Some(code) = params.get("code") else {
+            respond(
+                &mut stream,
+                "400 Bad Request",
+                "Authorization failed: no code.",
+            )
+            .await;
+            return Err(McpRigIntegrationError::AuthError(
+                "authorization response had no code".to_string(),
+            ));
+        };
+
+        respond(
+            &mut stream,
+            "200 OK",
+            "Authorization complete. You can close this window.",
+        )
+        .await;
+        return Ok(code.clone());
+    }
+}
+
+/// Read an HTTP request head and return the request target of a GET.
+async fn read_request_target(stream: &mut TcpStream) -> Option<String> {
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < 16 * 1024 {
+        let read = stream.read(&mut buffer).await.ok()?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let mut parts = head.lines().next()?.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => Some(target.to_string()),
+        _ => None,
+    }
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, message: &str) {
+    let body = format!(
+        "<!doctype html><html><body><p>{}</p></body></html>",
+        message
+    );
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn auth_error(context: &str, e: impl std::fmt::Display) -> McpRigIntegrationError {
+    McpRigIntegrationError::AuthError(format!("{}: {}", context, e))
+}
@@ -0,0 +1,214 @@
+// src/cache.rs
+
+//! Result caching for read-only MCP tools.
+//!
+//! Agents often call the same read-only tool with the same arguments several
+//! times in one conversation. A `ResultCache` shared between adapters stores
+//! successful results keyed by client ID, tool name and canonicalized
+//! arguments, with a time-to-live and a bound on the number of entries.
+//!
+//! Servers can't mark their tools read-only through mcp-spec 0.1.0, so only
+//! tools marked cacheable, with `McpToolAdapter::with_cacheable` or the
+//! `cacheable` config setting, are served from the cache. A call to any
+//! other tool is treated as possibly mutating and drops the cached results
+//! of every tool on the same client. Each invalidation also bumps the
+//! client's generation, so a cacheable call that was in flight during the
+//! mutation does not store its possibly stale result afterwards.
+
+use crate::cassette::normalize_arguments;
+use mcp_spec::{protocol::CallToolResult, Content};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Identifies a cached call.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    client: String,
+    tool: String,
+    args: String,
+}
+
+struct CacheEntry {
+    content: Vec<Content>,
+    inserted: Instant,
+    last_used: Instant,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Number of invalidations per client
+    generations: HashMap<String, u64>,
+}
+
+/// Shared cache of tool results.
+///
+/// Clones share the same storage, so one cache can serve every adapter.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let cache = ResultCache::new(256, Duration::from_secs(300));
+/// register_mcp_tools_with(client, &mut builder, model, &mut names, |adapter| {
+///     let cacheable = matches!(adapter.tool_name(), "read_file" | "list_directory");
+///     adapter
+///         .with_client_id("filesystem")
+///         .with_cache(cache.clone())
+///         .with_cacheable(cacheable)
+/// })
+/// .await?;
+/// ```
+#[derive(Clone)]
+pub struct ResultCache {
+    state: Arc<Mutex<CacheState>>,
+    /// Maximum number of cached results
+    max_entries: usize,
+    /// How long a result stays valid
+    ttl: Duration,
+}
+
+impl Default for ResultCache {
+    fn default() -> Self {
+        Self::new(128, Duration::from_secs(300))
+    }
+}
+
+impl ResultCache {
+    /// Create a cache holding at most `max_entries` results for `ttl` each.
+    pub fn new(max_entries: usize, ttl: Duration) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(CacheState::default())),
+            max_entries: max_entries.max(1),
+            ttl,
+        }
+    }
+
+    fn key(client: &str, tool: &str, args: &Value) -> CacheKey {
+        CacheKey {
+            client: client.to_string(),
+            tool: tool.to_string(),
+            args: normalize_arguments(args).to_string(),
+        }
+    }
+
+    /// Get a cached result if it has not expired
+    pub fn get(&self, client: &str, tool: &str, args: &Value) -> Option<CallToolResult> {
+        let key = Self::key(client, tool, args);
+        let entries = &mut self.state.lock().unwrap().entries;
+        let now = Instant::now();
+        let expired = match entries.get_mut(&key) {
+            Some(entry) if now.duration_since(entry.inserted) < self.ttl => {
+                entry.last_used = now;
+                return Some(CallToolResult {
+                    content: entry.content.clone(),
+                    is_error: None,
+                });
+            }
+            Some(_) => true,
+            None => false,
+        };
+        if expired {
+            entries.remove(&key);
+        }
+        None
+    }
+
+    /// Get a client's current generation.
+    ///
+    /// Read it before calling the tool and pass it to `insert_if_current`.
+    pub fn generation(&self, client: &str) -> u64 {
+        let state = self.state.lock().unwrap();
+        state.generations.get(client).copied().unwrap_or(0)
+    }
+
+    /// Store the content of a successful result.
+    ///
+    /// Expired entries are evicted first, then the least recently used ones.
+    pub fn insert(&self, client: &str, tool: &str, args: &Value, content: Vec<Content>) {
+        let entries = &mut self.state.lock().unwrap().entries;
+        self.insert_entry(entries, Self::key(client, tool, args), content);
+    }
+
+    /// Store a result unless the client was invalidated since `generation`.
+    ///
+    /// Returns whether the result was stored.
+    pub fn insert_if_current(
+        &self,
+        client: &str,
+        tool: &str,
+        args: &Value,
+        content: Vec<Content>,
+        generation: u64,
+    ) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.generations.get(client).copied().unwrap_or(0) != generation {
+            return false;
+        }
+        self.insert_entry(&mut state.entries, Self::key(client, tool, args), content);
+        true
+    }
+
+    fn insert_entry(
+        &self,
+        entries: &mut HashMap<CacheKey, CacheEntry>,
+        key: CacheKey,
+        content: Vec<Content>,
+    ) {
+        let now = Instant::now();
+
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            entries.retain(|_, entry| now.duration_since(entry.inserted) < self.ttl);
+        }
+        while entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => entries.remove(&oldest),
+                None => break,
+            };
+        }
+
+        entries.insert(
+            key,
+            CacheEntry {
+                content,
+                inserted: now,
+                last_used: now,
+            },
+        );
+    }
+
+    /// Drop every cached result for a client and bump its generation
+    pub fn invalidate_client(&self, client: &str) {
+        let mut state = self.state.lock().unwrap();
+        *state.generations.entry(client.to_string()).or_default() += 1;
+        let entries = &mut state.entries;
+        let before = entries.len();
+        entries.retain(|key, _| key.client != client);
+        let dropped = before - entries.len();
+        if dropped > 0 {
+            tracing::debug!(client, dropped, "invalidated cached tool results");
+        }
+    }
+
+    /// Drop every cached result
+    pub fn clear(&self) {
+        self.state.lock().unwrap().entries.clear();
+    }
+
+    /// Get the number of cached results, including expired ones not yet evicted
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    /// Check if the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,174 @@
+// src/cancellation.rs
+
+//! Cancellation support for in-flight MCP requests.
+//!
+//! `McpClient` assigns JSON-RPC request IDs internally and does not expose
+//! them, so cancelling a tool call needs some help from the transport layer.
+//! The connection manager wraps every transport handle in a `TrackedHandle`,
+//! which records the IDs of requests sent from inside a tracked call scope.
+//! When a tool call is abandoned, `McpToolAdapter` uses the client's
+//! `RequestCanceller` to send a `notifications/cancelled` message for each
+//! outstanding request.
+
+use crate::pagination::record_prompt_cursor;
+use mcp_client::transport::{Error as TransportError, TransportHandle};
+use mcp_spec::protocol::{JsonRpcMessage, JsonRpcNotification};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+tokio::task_local! {
+    static IN_FLIGHT: InFlight;
+}
+
+/// Request IDs sent from within a single tool call.
+#[derive(Clone, Default)]
+pub(crate) struct InFlight(Arc<Mutex<Vec<u64>>>);
+
+impl InFlight {
+    /// Run `future` with request tracking enabled for this set of IDs.
+    pub(crate) async fn scope<F: std::future::Future>(self, future: F) -> F::Output {
+        IN_FLIGHT.scope(self, future).await
+    }
+
+    fn push(&self, id: u64) {
+        self.0.lock().unwrap().push(id);
+    }
+
+    fn remove(&self, id: u64) {
+        self.0.lock().unwrap().retain(|pending| *pending != id);
+    }
+
+    fn take(&self) -> Vec<u64> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+/// Sends cancellation notifications for in-flight requests.
+///
+/// Implementations are obtained from `McpConnectionManager::get_canceller`
+/// and attached to tool adapters with `McpToolAdapter::with_canceller`.
+#[async_trait::async_trait]
+pub trait RequestCanceller: Send + Sync {
+    /// Notify the server that the request with the given ID was cancelled.
+    async fn cancel(&self, request_id: u64, reason: &str);
+}
+
+/// Transport handle wrapper that records request IDs for cancellation.
+///
+/// It also records prompt listing cursors for `list_all_prompts`.
+#[derive(Clone)]
+pub(crate) struct TrackedHandle<H> {
+    inner: H,
+}
+
+impl<H> TrackedHandle<H> {
+    pub(crate) fn new(inner: H) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait::async_trait]
+impl<H: TransportHandle> TransportHandle for TrackedHandle<H> {
+    async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, TransportError> {
+        let lists_prompts = matches!(
+            &message,
+            JsonRpcMessage::Request(request) if request.method == "prompts/list"
+        );
+        let tracked = match &message {
+            JsonRpcMessage::Request(request) => request.id.and_then(|id| {
+                IN_FLIGHT
+                    .try_with(|in_flight| {
+                        in_flight.push(id);
+                        (in_flight.clone(), id)
+                    })
+                    .ok()
+            }),
+            _ => None,
+        };
+
+        let response = self.inner.send(message).await;
+
+        if let Some((in_flight, id)) = tracked {
+            in_flight.remove(id);
+        }
+        if let (true, Ok(response)) = (lists_prompts, &response) {
+            record_prompt_cursor(response);
+        }
+        response
+    }
+}
+
+#[async_trait::async_trait]
+impl<H: TransportHandle> RequestCanceller for TrackedHandle<H> {
+    async fn cancel(&self, request_id: u64, reason: &str) {
+        let notification = JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: "notifications/cancelled".to_string(),
+            params: Some(json!({ "requestId": request_id, "reason": reason })),
+        });
+
+        if let Err(e) = self.inner.send(notification).await {
+            tracing::warn!(request_id, error = %e, "failed to send cancellation notification");
+        }
+    }
+}
+
+/// Guard around an in-flight tool call that cancels it when dropped.
+///
+/// The call future is polled inline by the caller, so dropping it stops the
+/// call locally. If the guard is dropped before `disarm` is called, a
+/// cancellation notification is also sent for every request the call still
+/// had in flight.
+pub(crate) struct CallGuard {
+    in_flight: InFlight,
+    canceller: Option<Arc<dyn RequestCanceller>>,
+    armed: bool,
+}
+
+impl CallGuard {
+    pub(crate) fn new(in_flight: InFlight, canceller: Option<Arc<dyn RequestCanceller>>) -> Self {
+        Self {
+            in_flight,
+            canceller,
+            armed: true,
+        }
+    }
+
+    /// Mark the call as finished so dropping the guard has no effect.
+    pub(crate) fn disarm(&mut self) {
+        self.armed = false;
+    }
+
+    /// Notify the server about the call's outstanding requests.
+    pub(crate) fn cancel(&mut self, reason: &str) {
+        if !self.armed {
+            return;
+        }
+        self.armed = false;
+
+        let pending = self.in_flight.take();
+        let Some(canceller) = self.canceller.clone() else {
+            return;
+        };
+        if pending.is_empty() {
+            return;
+        }
+
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!("no tokio runtime available to send cancellation notifications");
+            return;
+        };
+        let reason = reason.to_string();
+        runtime.spawn(async move {
+            for request_id in pending {
+                canceller.cancel(request_id, &reason).await;
+            }
+        });
+    }
+}
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        self.cancel("the caller dropped the request");
+    }
+}
@@ -0,0 +1,557 @@
+// src/cassette.rs
+
+//! Record-and-replay cassettes for MCP sessions.
+//!
+//! This module provides two `McpClientTrait` implementations that make agent
+//! behavior reproducible in regression tests:
+//!
+//! - `RecordingClient` wraps a live client and records every request and its
+//!   response, writing them to a JSON cassette file when finished.
+//! - `ReplayClient` serves the responses stored in a cassette, matching calls
+//!   on the method, the tool name and the normalized arguments.
+//!
+//! Both clients can be registered with `McpConnectionManager::insert_client`
+//! or passed directly to `register_mcp_tools`.
+
+use crate::error::McpRigIntegrationError;
+use mcp_client::{
+    client::{ClientCapabilities, ClientInfo},
+    Error as McpClientError, McpClientTrait,
+};
+use mcp_spec::protocol::{
+    CallToolResult, GetPromptResult, InitializeResult, ListPromptsResult, ListResourcesResult,
+    ListToolsResult, ReadResourceResult,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tokio::sync::Mutex;
+
+/// A recorded MCP session.
+///
+/// Cassettes are stored as pretty-printed JSON so they can be reviewed and
+/// edited by hand alongside the tests that use them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    /// Interactions in the order they were recorded
+    pub interactions: Vec<Interaction>,
+}
+
+/// A single request/response pair stored in a cassette.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The MCP method, e.g. `tools/call`
+    pub method: String,
+    /// The normalized request parameters
+    pub params: Value,
+    /// The response returned by the server
+    pub response: RecordedResponse,
+}
+
+/// The outcome of a recorded request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RecordedResponse {
+    /// The request succeeded with the given result
+    Ok { result: Value },
+    /// The request failed with the given error message
+    Err {
+        /// The error message
+        error: String,
+        /// What kind of error it was, so retries and timeouts replay faithfully
+        #[serde(default)]
+        kind: RecordedErrorKind,
+    },
+}
+
+/// The kind of a recorded client error.
+///
+/// Cassettes recorded before kinds were kept replay as `Other`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedErrorKind {
+    /// A JSON-RPC error response with the given code
+    Rpc { code: i32 },
+    /// The transport failed
+    Transport,
+    /// The request timed out
+    Timeout,
+    /// The service was not ready
+    NotReady,
+    /// Anything else, replayed as an unexpected response
+    #[default]
+    Other,
+}
+
+impl RecordedResponse {
+    fn from_error(error: &McpClientError) -> Self {
+        let (error, kind) = match error {
+            McpClientError::RpcError { code, message } => {
+                (message.clone(), RecordedErrorKind::Rpc { code: *code })
+            }
+            McpClientError::Transport(e) => (e.to_string(), RecordedErrorKind::Transport),
+            McpClientError::Timeout(_) => (error.to_string(), RecordedErrorKind::Timeout),
+            McpClientError::NotReady => (error.to_string(), RecordedErrorKind::NotReady),
+            other => (other.to_string(), RecordedErrorKind::Other),
+        };
+        Self::Err { error, kind }
+    }
+
+    fn to_error(error: &str, kind: &RecordedErrorKind) -> McpClientError {
+        match kind {
+            RecordedErrorKind::Rpc { code } => McpClientError::RpcError {
+                code: *code,
+                message: error.to_string(),
+            },
+            RecordedErrorKind::Transport => McpClientError::Transport(
+                mcp_client::transport::Error::Io(std::io::Error::other(error.to_string())),
+            ),
+            RecordedErrorKind::Timeout => {
+                McpClientError::Timeout(tower::timeout::error::Elapsed::new())
+            }
+            RecordedErrorKind::NotReady => McpClientError::NotReady,
+            RecordedErrorKind::Other => McpClientError::UnexpectedResponse(error.to_string()),
+        }
+    }
+}
+
+impl Cassette {
+    /// Load a cassette from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, McpRigIntegrationError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            McpRigIntegrationError::CassetteError(format!(
+                "failed to read cassette {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Write the cassette to a JSON file, replacing any existing content.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), McpRigIntegrationError> {
+        let path = path.as_ref();
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, contents).map_err(|e| {
+            McpRigIntegrationError::CassetteError(format!(
+                "failed to write cassette {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+}
+
+/// Normalize a JSON value for matching.
+///
+/// Object keys are sorted and `null` members are dropped, so arguments that
+/// differ only in key order or in explicitly-null optional fields match the
+/// same recording.
+pub fn normalize_arguments(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().filter(|(_, v)| !v.is_null()).collect();
+            entries.sort_by_key(|(key, _)| *key);
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k.clone(), normalize_arguments(v)))
+                    .collect::<Map<_, _>>(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(normalize_arguments).collect()),
+        other => other.clone(),
+    }
+}
+
+fn call_tool_params(name: &str, arguments: &Value) -> Value {
+    json!({ "name": name, "arguments": normalize_arguments(arguments) })
+}
+
+/// MCP client wrapper that records every request and response to a cassette.
+///
+/// The wrapped client must already be initialized. Call `save` to write the
+/// cassette; it takes `&self`, so keep an `Arc<RecordingClient>` next to the
+/// one handed to the connection manager. `finish` does the same for a
+/// recorder that is owned outright.
+///
+/// A recorder dropped with unsaved interactions writes them as a best
+/// effort, with blocking file I/O on the dropping thread; one that recorded
+/// nothing leaves the file alone.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use mcp_rig::{McpConnectionManager, RecordingClient};
+/// use std::sync::Arc;
+///
+/// # async fn example(manager: &mut McpConnectionManager) -> Result<(), Box<dyn std::error::Error>> {
+/// let git_client = manager.get_client("git-client").ok_or("Git client not found")?;
+/// let recorder = Arc::new(RecordingClient::new(git_client, "tests/cassettes/git.json"));
+/// manager.insert_client("git-client".to_string(), recorder.clone());
+///
+/// // ... run the agent ...
+///
+/// recorder.save().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct RecordingClient {
+    /// The live client that requests are forwarded to
+    inner: Arc<dyn McpClientTrait>,
+    /// Where the cassette is written
+    path: PathBuf,
+    /// Interactions recorded so far
+    cassette: Mutex<Cassette>,
+    /// Whether interactions were recorded since the last save
+    dirty: AtomicBool,
+}
+
+impl RecordingClient {
+    /// Create a recorder that writes to `path`, starting from an empty cassette.
+    pub fn new(inner: Arc<dyn McpClientTrait>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+            cassette: Mutex::new(Cassette::default()),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// Get a copy of the interactions recorded so far.
+    pub async fn cassette(&self) -> Cassette {
+        self.cassette.lock().await.clone()
+    }
+
+    /// Write the interactions recorded so far to the cassette file.
+    ///
+    /// Recording continues afterwards, so this can be called again later.
+    pub async fn save(&self) -> Result<(), McpRigIntegrationError> {
+        let contents = {
+            let cassette = self.cassette.lock().await;
+            // Cleared under the lock so a concurrent recording marks it again
+            self.dirty.store(false, Ordering::SeqCst);
+            serde_json::to_string_pretty(&*cassette)?
+        };
+        tokio::fs::write(&self.path, contents).await.map_err(|e| {
+            self.dirty.store(true, Ordering::SeqCst);
+            McpRigIntegrationError::CassetteError(format!(
+                "failed to write cassette {}: {}",
+                self.path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Write the cassette and stop recording.
+    pub async fn finish(self) -> Result<(), McpRigIntegrationError> {
+        self.save().await
+    }
+
+    async fn record<T: Serialize>(
+        &self,
+        method: &str,
+        params: Value,
+        result: &Result<T, McpClientError>,
+    ) {
+        let response = match result {
+            Ok(value) => match serde_json::to_value(value) {
+                Ok(result) => RecordedResponse::Ok { result },
+                Err(e) => {
+                    tracing::warn!(method, error = %e, "failed to serialize MCP response for cassette");
+                    return;
+                }
+            },
+            Err(e) => RecordedResponse::from_error(e),
+        };
+
+        let mut cassette = self.cassette.lock().await;
+        cassette.interactions.push(Interaction {
+            method: method.to_string(),
+            params,
+            response,
+        });
+        self.dirty.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for RecordingClient {
+    fn drop(&mut self) {
+        if !*self.dirty.get_mut() {
+            return;
+        }
+        tracing::warn!(
+            path = %self.path.display(),
+            "recorder dropped with unsaved interactions; call finish() to save them"
+        );
+        if let Err(e) = self.cassette.get_mut().save(&self.path) {
+            tracing::warn!(error = %e, "failed to persist cassette");
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl McpClientTrait for RecordingClient {
+    async fn initialize(
+        &mut self,
+        _info: ClientInfo,
+        _capabilities: ClientCapabilities,
+    ) -> Result<InitializeResult, McpClientError> {
+        Err(McpClientError::UnexpectedResponse(
+            "RecordingClient wraps an already initialized client".to_string(),
+        ))
+    }
+
+    async fn list_resources(
+        &self,
+        next_cursor: Option<String>,
+    ) -> Result<ListResourcesResult, McpClientError> {
+        let params = json!({ "cursor": next_cursor });
+        let result = self.inner.list_resources(next_cursor).await;
+        self.record("resources/list", params, &result).await;
+        result
+    }
+
+    async fn read_resource(&self, uri: &str) -> Result<ReadResourceResult, McpClientError> {
+        let result = self.inner.read_resource(uri).await;
+        self.record("resources/read", json!({ "uri": uri }), &result)
+            .await;
+        result
+    }
+
+    async fn list_tools(
+        &self,
+        next_cursor: Option<String>,
+    ) -> Result<ListToolsResult, McpClientError> {
+        let params = json!({ "cursor": next_cursor });
+        let result = self.inner.list_tools(next_cursor).await;
+        self.record("tools/list", params, &result).await;
+        result
+    }
+
+    async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<CallToolResult, McpClientError> {
+        let params = call_tool_params(name, &arguments);
+        let result = self.inner.call_tool(name, arguments).await;
+        self.record("tools/call", params, &result).await;
+        result
+    }
+
+    async fn list_prompts(
+        &self,
+        next_cursor: Option<String>,
+    ) -> Result<ListPromptsResult, McpClientError> {
+        let params = json!({ "cursor": next_cursor });
+        let result = self.inner.list_prompts(next_cursor).await;
+        self.record("prompts/list", params, &result).await;
+        result
+    }
+
+    async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<GetPromptResult, McpClientError> {
+        let params = json!({ "name": name, "arguments": normalize_arguments(&arguments) });
+        let result = self.inner.get_prompt(name, arguments).await;
+        self.record("prompts/get", params, &result).await;
+        result
+    }
+}
+
+/// What a `ReplayClient` does when a request has no matching recording.
+#[derive(Clone)]
+pub enum MismatchMode {
+    /// Fail the request with an error describing the unmatched call
+    Strict,
+    /// Forward the request to a live client
+    Fallthrough(Arc<dyn McpClientTrait>),
+}
+
+/// MCP client that serves responses from a cassette.
+///
+/// Requests are matched on the method and normalized parameters; for tool
+/// calls that means the tool name plus the normalized arguments. When the same
+/// request was recorded several times, the recordings are replayed in order
+/// and the last one is repeated once they run out.
+pub struct ReplayClient {
+    /// Recorded responses grouped by match key, in recording order
+    recordings: HashMap<String, Vec<RecordedResponse>>,
+    /// How many times each match key has been served
+    cursors: Mutex<HashMap<String, usize>>,
+    /// Behavior for requests without a recording
+    mismatch: MismatchMode,
+}
+
+impl ReplayClient {
+    /// Create a strict replay client from a cassette.
+    pub fn new(cassette: Cassette) -> Self {
+        Self::with_mismatch_mode(cassette, MismatchMode::Strict)
+    }
+
+    /// Create a replay client with the given mismatch behavior.
+    pub fn with_mismatch_mode(cassette: Cassette, mismatch: MismatchMode) -> Self {
+        let mut recordings: HashMap<String, Vec<RecordedResponse>> = HashMap::new();
+        for interaction in cassette.interactions {
+            recordings
+                .entry(Self::key(&interaction.method, &interaction.params))
+                .or_default()
+                .push(interaction.response);
+        }
+
+        Self {
+            recordings,
+            cursors: Mutex::new(HashMap::new()),
+            mismatch,
+        }
+    }
+
+    /// Load a cassette file and create a strict replay client from it.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, McpRigIntegrationError> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+
+    fn key(method: &str, params: &Value) -> String {
+        format!("{} {}", method, normalize_arguments(params))
+    }
+
+    /// Look up the next recorded response for a request, if any.
+    async fn replay<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &Value,
+    ) -> Option<Result<T, McpClientError>> {
+        let key = Self::key(method, params);
+        let responses = self.recordings.get(&key)?;
+
+        let mut cursors = self.cursors.lock().await;
+        let cursor = cursors.entry(key).or_insert(0);
+        let response = &responses[(*cursor).min(responses.len() - 1)];
+        *cursor += 1;
+
+        Some(match response {
+            RecordedResponse::Ok { result } => {
+                serde_json::from_value(result.clone()).map_err(McpClientError::from)
+            }
+            RecordedResponse::Err { error, kind } => Err(RecordedResponse::to_error(error, kind)),
+        })
+    }
+
+    fn mismatch(
+        &self,
+        method: &str,
+        params: &Value,
+    ) -> Result<&dyn McpClientTrait, McpClientError> {
+        match &self.mismatch {
+            MismatchMode::Strict => Err(McpClientError::UnexpectedResponse(format!(
+                "no cassette recording for {} {}",
+                method, params
+            ))),
+            MismatchMode::Fallthrough(client) => {
+                tracing::debug!(method, %params, "no cassette recording, falling through to live client");
+                Ok(client.as_ref())
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl McpClientTrait for ReplayClient {
+    async fn initialize(
+        &mut self,
+        _info: ClientInfo,
+        _capabilities: ClientCapabilities,
+    ) -> Result<InitializeResult, McpClientError> {
+        Err(McpClientError::UnexpectedResponse(
+            "ReplayClient does not need to be initialized".to_string(),
+        ))
+    }
+
+    async fn list_resources(
+        &self,
+        next_cursor: Option<String>,
+    ) -> Result<ListResourcesResult, McpClientError> {
+        let params = json!({ "cursor": next_cursor });
+        if let Some(result) = self.replay("resources/list", &params).await {
+            return result;
+        }
+        self.mismatch("resources/list", &params)?
+            .list_resources(next_cursor)
+            .await
+    }
+
+    async fn read_resource(&self, uri: &str) -> Result<ReadResourceResult, McpClientError> {
+        let params = json!({ "uri": uri });
+        if let Some(result) = self.replay("resources/read", &params).await {
+            return result;
+        }
+        self.mismatch("resources/read", &params)?
+            .read_resource(uri)
+            .await
+    }
+
+    async fn list_tools(
+        &self,
+        next_cursor: Option<String>,
+    ) -> Result<ListToolsResult, McpClientError> {
+        let params = json!({ "cursor": next_cursor });
+        if let Some(result) = self.replay("tools/list", &params).await {
+            return result;
+        }
+        self.mismatch("tools/list", &params)?
+            .list_tools(next_cursor)
+            .await
+    }
+
+    async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<CallToolResult, McpClientError> {
+        let params = call_tool_params(name, &arguments);
+        if let Some(result) = self.replay("tools/call", &params).await {
+            return result;
+        }
+        self.mismatch("tools/call", &params)?
+            .call_tool(name, arguments)
+            .await
+    }
+
+    async fn list_prompts(
+        &self,
+        next_cursor: Option<String>,
+    ) -> Result<ListPromptsResult, McpClientError> {
+        let params = json!({ "cursor": next_cursor });
+        if let Some(result) = self.replay("prompts/list", &params).await {
+            return result;
+        }
+        self.mismatch("prompts/list", &params)?
+            .list_prompts(next_cursor)
+            .await
+    }
+
+    async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<GetPromptResult, McpClientError> {
+        let params = json!({ "name": name, "arguments": normalize_arguments(&arguments) });
+        if let Some(result) = self.replay("prompts/get", &params).await {
+            return result;
+        }
+        self.mismatch("prompts/get", &params)?
+            .get_prompt(name, arguments)
+            .await
+    }
+}
@@ -0,0 +1,265 @@
+// src/chat.rs
+
+//! Multi-step chat sessions with tool calls.
+//!
+//! Rig's `Chat` implementation returns the output of the first tool call as
+//! the agent's answer. `ChatSession` instead runs every tool the model asks
+//! for, sends the results back and repeats until the model answers in text,
+//! reporting each step to a callback so hosts can show tool activity inline.
+//!
+//! The conversation is kept as Rig messages, including tool calls and their
+//! results, and can be saved to and restored from a JSON transcript file.
+
+use crate::error::McpRigIntegrationError;
+use rig::{
+    agent::Agent,
+    completion::{AssistantContent, Completion, CompletionModel, Message},
+    message::{ToolResultContent, UserContent},
+    OneOrMany,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// How many model requests one reply may take unless configured.
+pub(crate) const DEFAULT_MAX_STEPS: usize = 10;
+
+/// Something that happened while the agent worked on a reply.
+#[derive(Debug, Clone, Copy)]
+pub enum ChatEvent<'a> {
+    /// The model wrote text alongside its tool calls
+    Text(&'a str),
+    /// The model called a tool
+    ToolCall {
+        /// The tool name as the model sees it
+        name: &'a str,
+        /// The arguments the model passed
+        arguments: &'a Value,
+    },
+    /// A tool call finished; the output is sent back to the model
+    ToolResult {
+        /// The tool name as the model sees it
+        name: &'a str,
+        /// The tool's output
+        output: &'a str,
+    },
+    /// A tool call failed; the error is sent back to the model
+    ToolError {
+        /// The tool name as the model sees it
+        name: &'a str,
+        /// The error message
+        error: &'a str,
+    },
+}
+
+/// A saved conversation.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transcript {
+    /// The messages in order, including tool calls and results
+    pub messages: Vec<Message>,
+}
+
+impl Transcript {
+    /// Load a transcript from disk, or an empty one if the file doesn't exist.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, McpRigIntegrationError> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(McpRigIntegrationError::Other(format!(
+                "failed to read transcript {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    /// Write the transcript to disk as pretty-printed JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), McpRigIntegrationError> {
+        let path = path.as_ref();
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, contents).map_err(|e| {
+            McpRigIntegrationError::Other(format!(
+                "failed to write transcript {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+}
+
+/// A conversation with an agent that runs tools until the model answers.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut session = ChatSession::new(agent).with_transcript(Transcript::load("chat.json")?);
+/// let answer = session
+///     .send("What changed in the last commit?", |event| println!("{:?}", event))
+///     .await?;
+/// session.transcript().save("chat.json")?;
+/// ```
+pub struct ChatSession<M: CompletionModel> {
+    /// The agent answering, with its tools
+    agent: Agent<M>,
+    /// The conversation so far
+    transcript: Transcript,
+    /// How many model requests one reply may take
+    max_steps: usize,
+}
+
+impl<M: CompletionModel> ChatSession<M> {
+    /// Start an empty conversation with `agent`.
+    pub fn new(agent: Agent<M>) -> Self {
+        Self {
+            agent,
+            transcript: Transcript::default(),
+            max_steps: DEFAULT_MAX_STEPS,
+        }
+    }
+
+    /// Continue a saved conversation.
+    pub fn with_transcript(mut self, transcript: Transcript) -> Self {
+        self.transcript = transcript;
+        self
+    }
+
+    /// Set how many model requests one reply may take before giving up.
+    ///
+    /// Each round of tool calls takes one request.
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps.max(1);
+        self
+    }
+
+    /// Get the conversation so far
+    pub fn transcript(&self) -> &Transcript {
+        &self.transcript
+    }
+
+    /// Forget the conversation so far
+    pub fn clear(&mut self) {
+        self.transcript.messages.clear();
+    }
+
+    /// Send a user message and return the model's final answer.
+    ///
+    /// Tool calls are run in the order the model made them, and each one is
+    /// reported to `on_event`. Tool failures are sent back to the model as
+    /// results so it can recover. If the reply fails, the conversation is
+    /// left as it was before the message.
+    pub async fn send<F>(
+        &mut self,
+        message: &str,
+        mut on_event: F,
+    ) -> Result<String, McpRigIntegrationError>
+    where
+        F: FnMut(ChatEvent<'_>),
+    {
+        let mut history = self.transcript.messages.clone();
+        let answer = run_turn(
+            &self.agent,
+            &mut history,
+            message,
+            self.max_steps,
+            &mut on_event,
+        )
+        .await?;
+        self.transcript.messages = history;
+        Ok(answer)
+    }
+}
+
+/// Answer one user message, running tools until the model replies in text.
+///
+/// The prompt, tool calls, results and answer are appended to `history`.
+pub(crate) async fn run_turn<M, F>(
+    agent: &Agent<M>,
+    history: &mut Vec<Message>,
+    message: &str,
+    max_steps: usize,
+    on_event: &mut F,
+) -> Result<String, McpRigIntegrationError>
+where
+    M: CompletionModel,
+    F: FnMut(ChatEvent<'_>),
+{
+    let mut prompt = Message::user(message);
+
+    for _ in 0..max_steps {
+        let response = agent
+            .completion(prompt.clone(), history.clone())
+            .await
+            .map_err(rig_error)?
+            .send()
+            .await
+            .map_err(rig_error)?;
+        history.push(prompt);
+        history.push(Message::Assistant {
+            content: response.choice.clone(),
+        });
+
+        let mut text = Vec::new();
+        let mut calls = Vec::new();
+        for content in response.choice.iter() {
+            match content {
+                AssistantContent::Text(t) => text.push(t.text.as_str()),
+                AssistantContent::ToolCall(call) => calls.push(call),
+            }
+        }
+        let text = text.join("\n");
+        if calls.is_empty() {
+            return Ok(text);
+        }
+        if !text.trim().is_empty() {
+            on_event(ChatEvent::Text(&text));
+        }
+
+        let mut results = Vec::new();
+        for call in calls {
+            let name = call.function.name.as_str();
+            on_event(ChatEvent::ToolCall {
+                name,
+                arguments: &call.function.arguments,
+            });
+            let output = match agent
+                .tools
+                .call(name, call.function.arguments.to_string())
+                .await
+            {
+                Ok(output) => {
+                    on_event(ChatEvent::ToolResult {
+                        name,
+                        output: &output,
+                    });
+                    output
+                }
+                Err(e) => {
+                    let error = e.to_string();
+                    on_event(ChatEvent::ToolError {
+                        name,
+                        error: &error,
+                    });
+                    format!("Error: {}", error)
+                }
+            };
+            results.push(UserContent::tool_result(
+                call.id.clone(),
+                OneOrMany::one(ToolResultContent::text(output)),
+            ));
+        }
+        prompt = Message::User {
+            // There is at least one call, so at least one result
+            content: OneOrMany::many(results).map_err(rig_error)?,
+        };
+    }
+
+    Err(McpRigIntegrationError::RigError(format!(
+        "the model did not answer within {} steps",
+        max_steps
+    )))
+}
+
+fn rig_error(e: impl std::fmt::Display) -> McpRigIntegrationError {
+    McpRigIntegrationError::RigError(e.to_string())
+}
@@ -0,0 +1,300 @@
+// src/config.rs
+
+//! Configuration file support for MCP servers.
+//!
+//! The config file uses the same `mcpServers` layout as common MCP hosts, so
+//! existing server entries can be copied over unchanged. Each entry may also
+//! carry a `tools` section with per-tool settings that are applied when the
+//! tool adapters are built.
+//!
+//! ```json
+//! {
+//!   "mcpServers": {
+//!     "git": {
+//!       "command": "uvx",
+//!       "args": ["mcp-server-git"],
+//!       "cwd": "/srv/repo",
+//!       "denyTools": ["git_push", "git_reset*"],
+//!       "limits": { "memoryBytes": 2147483648, "openFiles": 256 },
+//!       "tools": {
+//!         "git_log": { "appendDescription": "Prefer maxCount <= 20.", "cacheable": true },
+//!         "git_diff": { "outputBudget": { "limit": 2000, "unit": "tokens" } },
+//!         "git_commit": { "idempotent": false }
+//!       }
+//!     },
+//!     "echo": { "type": "sse", "url": "http://localhost:8000/sse" },
+//!     "search": { "url": "unix:///run/mcp/search.sock" }
+//!   },
+//!   "outputBudget": { "limit": 20000, "strategy": "headTail" }
+//! }
+//! ```
+
+use crate::adapter::McpToolAdapter;
+use crate::error::McpRigIntegrationError;
+use crate::glob::glob_match;
+use crate::overrides::ToolOverride;
+use crate::policy::{PolicyEngine, PolicyRule};
+use crate::transport::ResourceLimits;
+use crate::truncation::OutputBudget;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Top-level configuration file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpConfig {
+    /// Server entries keyed by client ID
+    #[serde(rename = "mcpServers", default)]
+    pub servers: BTreeMap<String, ServerConfig>,
+    /// Policy rules applied to every tool call
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policies: Vec<PolicyRule>,
+    /// Output budget for every tool unless the tool sets its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_budget: Option<OutputBudget>,
+}
+
+impl McpConfig {
+    /// Load a configuration file from disk.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, McpRigIntegrationError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            McpRigIntegrationError::ConfigError(format!(
+                "failed to read config {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::from_json(&contents)
+    }
+
+    /// Parse a configuration from a JSON string.
+    pub fn from_json(json: &str) -> Result<Self, McpRigIntegrationError> {
+        serde_json::from_str(json)
+            .map_err(|e| McpRigIntegrationError::ConfigError(format!("invalid config: {}", e)))
+    }
+
+    /// Get a server entry by ID
+    pub fn server(&self, id: &str) -> Option<&ServerConfig> {
+        self.servers.get(id)
+    }
+
+    /// Build a policy engine from the `policies` section
+    pub fn policy_engine(&self) -> PolicyEngine {
+        PolicyEngine::new(self.policies.clone())
+    }
+
+    /// Apply the global and per-tool settings for a server to an adapter.
+    ///
+    /// The adapter's client ID is set to `server` and the `policies`, if
+    /// any, are enforced on its calls. Per-tool settings take precedence
+    /// over global ones.
+    pub fn configure_adapter(&self, server: &str, mut adapter: McpToolAdapter) -> McpToolAdapter {
+        adapter = adapter.with_client_id(server);
+        if !self.policies.is_empty() {
+            adapter = adapter.with_policy(Arc::new(self.policy_engine()));
+        }
+        if let Some(budget) = self.output_budget {
+            adapter = adapter.with_output_budget(budget);
+        }
+        match self.server(server) {
+            Some(config) => config.configure_adapter(adapter),
+            None => adapter,
+        }
+    }
+}
+
+/// The transport used to reach a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    /// Spawn a child process and talk over stdin/stdout
+    Stdio,
+    /// Connect to a legacy HTTP+SSE endpoint
+    Sse,
+    /// Connect to a Streamable HTTP endpoint, falling back to SSE
+    #[serde(alias = "streamable-http", alias = "streamableHttp")]
+    Http,
+    /// Connect to a WebSocket endpoint
+    #[serde(alias = "ws")]
+    WebSocket,
+    /// Connect to a Unix domain socket, given as `unix:///path/to/socket`
+    Unix,
+    /// Connect to a TCP port, given as `tcp://host:port`
+    Tcp,
+}
+
+/// Configuration for a single MCP server.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerConfig {
+    /// Explicit transport; inferred from `command` or the `url` scheme when omitted
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<TransportKind>,
+    /// Program to run for stdio servers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Arguments passed to `command`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Environment variables set for `command`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// Working directory for `command`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    /// Start `command` with only `env` instead of inheriting the environment
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub clear_env: bool,
+    /// Resource limits for `command`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<ResourceLimits>,
+    /// Endpoint URL for network servers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Extra HTTP headers for network servers
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// Tool name patterns the gateway offers; every tool when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow_tools: Vec<String>,
+    /// Tool name patterns the gateway never offers, even if allowed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny_tools: Vec<String>,
+    /// Per-tool settings keyed by MCP tool name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tools: BTreeMap<String, ToolConfig>,
+}
+
+impl ServerConfig {
+    /// Determine the transport for this server.
+    pub fn transport(&self) -> Result<TransportKind, McpRigIntegrationError> {
+        match (self.kind, &self.command, &self.url) {
+            (Some(kind), _, _) => Ok(kind),
+            (None, Some(_), _) => Ok(TransportKind::Stdio),
+            (None, None, Some(url)) => Ok(match url.split_once(':').map(|(scheme, _)| scheme) {
+                Some("ws" | "wss") => TransportKind::WebSocket,
+                Some("unix") => TransportKind::Unix,
+                Some("tcp") => TransportKind::Tcp,
+                _ => TransportKind::Sse,
+            }),
+            (None, None, None) => Err(McpRigIntegrationError::ConfigError(
+                "server entry needs a `command` or a `url`".to_string(),
+            )),
+        }
+    }
+
+    /// Check a tool against `allowTools` and `denyTools`.
+    pub fn allows_tool(&self, name: &str) -> bool {
+        let allowed = self.allow_tools.is_empty()
+            || self
+                .allow_tools
+                .iter()
+                .any(|pattern| glob_match(pattern, name));
+        allowed
+            && !self
+                .deny_tools
+                .iter()
+                .any(|pattern| glob_match(pattern, name))
+    }
+
+    /// Get the settings for a tool by its MCP name
+    pub fn tool(&self, name: &str) -> Option<&ToolConfig> {
+        self.tools.get(name)
+    }
+
+    /// Apply this server's per-tool settings to an adapter.
+    ///
+    /// This is meant to be passed to `register_mcp_tools_with`:
+    ///
+    /// ```rust,ignore
+    /// let server = config.server("git").unwrap();
+    /// register_mcp_tools_with(client, &mut builder, model, &mut names, |a| server.configure_adapter(a))
+    ///     .await?;
+    /// ```
+    pub fn configure_adapter(&self, adapter: McpToolAdapter) -> McpToolAdapter {
+        match self.tool(adapter.tool_name()) {
+            Some(tool) => tool.apply(adapter),
+            None => adapter,
+        }
+    }
+}
+
+/// Per-tool settings from the config file.
+///
+/// Unknown keys are rejected, so a misspelled setting is reported instead
+/// of being ignored.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", try_from = "Map<String, Value>")]
+pub struct ToolConfig {
+    /// Description and schema overrides
+    #[serde(flatten)]
+    pub overrides: ToolOverride,
+    /// Whether calls need approval, overriding the approval gate's patterns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_approval: Option<bool>,
+    /// Limit on the size of the tool's results
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_budget: Option<OutputBudget>,
+    /// Whether the tool is read-only and its results may be cached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cacheable: Option<bool>,
+    /// Whether the tool may be retried after a transient failure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotent: Option<bool>,
+}
+
+/// Remove a key from a config object and deserialize its value.
+pub(crate) fn take_key<T: DeserializeOwned>(
+    map: &mut Map<String, Value>,
+    key: &str,
+) -> Result<Option<T>, serde_json::Error> {
+    map.remove(key).map(serde_json::from_value).transpose()
+}
+
+// serde can't combine `deny_unknown_fields` with the flattened overrides, so
+// the tool's own keys are taken out first and the rest must be overrides.
+impl TryFrom<Map<String, Value>> for ToolConfig {
+    type Error = serde_json::Error;
+
+    fn try_from(mut map: Map<String, Value>) -> Result<Self, Self::Error> {
+        let require_approval = take_key(&mut map, "requireApproval")?;
+        let output_budget = take_key(&mut map, "outputBudget")?;
+        let cacheable = take_key(&mut map, "cacheable")?;
+        let idempotent = take_key(&mut map, "idempotent")?;
+        Ok(Self {
+            overrides: serde_json::from_value(Value::Object(map))?,
+            require_approval,
+            output_budget,
+            cacheable,
+            idempotent,
+        })
+    }
+}
+
+impl ToolConfig {
+    /// Apply these settings to an adapter.
+    pub fn apply(&self, mut adapter: McpToolAdapter) -> McpToolAdapter {
+        if self.overrides != ToolOverride::default() {
+            adapter = adapter.with_override(self.overrides.clone());
+        }
+        if let Some(required) = self.require_approval {
+            adapter = adapter.with_approval_required(required);
+        }
+        if let Some(budget) = self.output_budget {
+            adapter = adapter.with_output_budget(budget);
+        }
+        if let Some(cacheable) = self.cacheable {
+            adapter = adapter.with_cacheable(cacheable);
+        }
+        if let Some(idempotent) = self.idempotent {
+            adapter = adapter.with_idempotent(idempotent);
+        }
+        adapter
+    }
+}
@@ -4,15 +4,49 @@
 //!
 //! This module provides a connection manager for MCP clients with different transport
 //! mechanisms. It simplifies the creation, storage, and retrieval of MCP clients,
-//! supporting various transport options such as stdio and SSE.
+//! supporting various transport options such as stdio, SSE, Streamable HTTP and
+//! WebSocket.
 
+use crate::approval::{ApprovalDecision, ApprovalHandler, ApprovalRequest};
+use crate::auth::AuthProvider;
+use crate::cancellation::{RequestCanceller, TrackedHandle};
+use crate::config::{McpConfig, ServerConfig, TransportKind};
 use crate::error::McpRigIntegrationError;
+use crate::lockfile::{PinMode, PinReport, PinnedClient, ToolChange, ToolLockfile};
+use crate::notifications::{NotificationSink, ServerNotification};
+use crate::server::McpServer;
+use crate::toolset::list_all_tools;
+#[cfg(unix)]
+use crate::transport::UnixSocketTransport;
+use crate::transport::{
+    ChannelTransport, HttpSession, ProcessTransport, StreamableHttpTransport, TcpTransport,
+    WebSocketTransport,
+};
 use mcp_client::{
     client::{ClientCapabilities, ClientInfo, McpClient, McpClientTrait},
-    transport::{SseTransport, StdioTransport, Transport},
+    transport::{SseTransport, Transport},
     McpService,
 };
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use serde_json::json;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+/// Tool definition pinning settings and state.
+struct ToolPinning {
+    /// Where the lockfile is stored
+    path: PathBuf,
+    /// The pinned tool definitions
+    lockfile: ToolLockfile,
+    /// How changed definitions are handled
+    mode: PinMode,
+    /// Reviews changed definitions in `PinMode::RequireApproval`
+    approver: Option<Arc<dyn ApprovalHandler>>,
+}
 
 /// Manager for MCP client connections.
 ///
@@ -58,7 +92,21 @@ use std::{collections::HashMap, sync::Arc, time::Duration};
 #[derive(Default)]
 pub struct McpConnectionManager {
     /// Map of client ID to client instance
-    clients: HashMap<String, Arc<Box<dyn McpClientTrait>>>,
+    clients: HashMap<String, Arc<dyn McpClientTrait>>,
+    /// Map of client ID to the canceller for its transport
+    cancellers: HashMap<String, Arc<dyn RequestCanceller>>,
+    /// Map of client ID to the config entry it was created from
+    server_configs: HashMap<String, ServerConfig>,
+    /// Tool definition pinning, if enabled
+    pinning: Option<ToolPinning>,
+    /// Map of client ID to the result of checking its pinned tools
+    pin_reports: HashMap<String, PinReport>,
+    /// Map of client ID to the session of Streamable HTTP clients
+    http_sessions: HashMap<String, HttpSession>,
+    /// Map of client ID to the provider authorizing its requests
+    auth_providers: HashMap<String, Arc<dyn AuthProvider>>,
+    /// Receives server notifications, if subscribed
+    notifications: Option<mpsc::UnboundedSender<ServerNotification>>,
     /// Default timeout for MCP services
     timeout: Duration,
 }
@@ -75,6 +123,13 @@ impl McpConnectionManager {
     pub fn new() -> Self {
         Self {
             clients: HashMap::new(),
+            cancellers: HashMap::new(),
+            server_configs: HashMap::new(),
+            pinning: None,
+            pin_reports: HashMap::new(),
+            http_sessions: HashMap::new(),
+            auth_providers: HashMap::new(),
+            notifications: None,
             timeout: Duration::from_secs(30),
         }
     }
@@ -94,11 +149,22 @@ impl McpConnectionManager {
     pub fn with_timeout(timeout: Duration) -> Self {
         Self {
             clients: HashMap::new(),
+            cancellers: HashMap::new(),
+            server_configs: HashMap::new(),
+            pinning: None,
+            pin_reports: HashMap::new(),
+            http_sessions: HashMap::new(),
+            auth_providers: HashMap::new(),
+            notifications: None,
             timeout,
         }
     }
 
-    /// Add a client using a StdioTransport
+    /// Add a client that runs a server process over stdio
+    ///
+    /// The process inherits this process's environment plus `env`, and its
+    /// stderr is forwarded to `tracing` under the client ID. Use
+    /// `add_process_client` for more control over how it runs.
     pub async fn add_stdio_client(
         &mut self,
         id: String,
@@ -107,7 +173,27 @@ impl McpConnectionManager {
         env: HashMap<String, String>,
         client_info: ClientInfo,
     ) -> Result<(), McpRigIntegrationError> {
-        let transport = StdioTransport::new(program, args, env);
+        let transport = ProcessTransport::new(program, args).with_envs(env);
+        self.add_process_client(id, transport, client_info).await
+    }
+
+    /// Add a client using a ProcessTransport.
+    ///
+    /// The client ID is attached to the server's stderr output unless the
+    /// transport already has one.
+    pub async fn add_process_client(
+        &mut self,
+        id: String,
+        transport: ProcessTransport,
+        client_info: ClientInfo,
+    ) -> Result<(), McpRigIntegrationError> {
+        let mut transport = match transport.client_id() {
+            Some(_) => transport,
+            None => transport.with_client_id(id.clone()),
+        };
+        if let Some(sink) = self.notification_sink(&id) {
+            transport = transport.with_notifications(sink);
+        }
         self.add_client(id, transport, client_info).await
     }
 
@@ -123,6 +209,258 @@ impl McpConnectionManager {
         self.add_client(id, transport, client_info).await
     }
 
+    /// Add a client using the Streamable HTTP transport.
+    ///
+    /// If the server rejects the connection because it only supports the
+    /// legacy HTTP+SSE transport, the client falls back to SSE on the same
+    /// URL. The session ID assigned by the server is available from
+    /// `get_http_session`.
+    pub async fn add_http_client(
+        &mut self,
+        id: String,
+        url: &str,
+        headers: HashMap<String, String>,
+        client_info: ClientInfo,
+    ) -> Result<(), McpRigIntegrationError> {
+        let transport =
+            self.authorize_http(&id, StreamableHttpTransport::new(url).with_headers(headers));
+        self.add_http_transport(id, transport, client_info).await
+    }
+
+    /// Add a Streamable HTTP client that resumes an existing session.
+    ///
+    /// If the server no longer knows the session, a new one is started.
+    pub async fn resume_http_client(
+        &mut self,
+        id: String,
+        url: &str,
+        headers: HashMap<String, String>,
+        session_id: String,
+        client_info: ClientInfo,
+    ) -> Result<(), McpRigIntegrationError> {
+        let transport = StreamableHttpTransport::new(url)
+            .with_headers(headers)
+            .with_session_id(session_id);
+        let transport = self.authorize_http(&id, transport);
+        self.add_http_transport(id, transport, client_info).await
+    }
+
+    fn authorize_http(
+        &self,
+        id: &str,
+        mut transport: StreamableHttpTransport,
+    ) -> StreamableHttpTransport {
+        if let Some(provider) = self.auth_providers.get(id) {
+            transport = transport.with_auth(Arc::clone(provider));
+        }
+        if let Some(sink) = self.notification_sink(id) {
+            transport = transport.with_notifications(sink);
+        }
+        transport
+    }
+
+    async fn add_http_transport(
+        &mut self,
+        id: String,
+        transport: StreamableHttpTransport,
+        client_info: ClientInfo,
+    ) -> Result<(), McpRigIntegrationError> {
+        let session = transport.session();
+        self.add_client(id.clone(), transport, client_info).await?;
+        self.http_sessions.insert(id, session);
+        Ok(())
+    }
+
+    /// Authorize requests to a server with `provider`.
+    ///
+    /// Call this before adding the client. The provider is used by
+    /// `add_http_client`, `resume_http_client`, `add_ws_client` and servers
+    /// with those transports in a config file. Legacy SSE clients cannot
+    /// send the header and are not authorized.
+    pub fn set_auth_provider(&mut self, id: impl Into<String>, provider: Arc<dyn AuthProvider>) {
+        self.auth_providers.insert(id.into(), provider);
+    }
+
+    /// Add a client using a WebSocketTransport.
+    ///
+    /// The connection is kept alive with pings and re-established, including
+    /// the MCP handshake, if it drops.
+    pub async fn add_ws_client(
+        &mut self,
+        id: String,
+        url: &str,
+        headers: HashMap<String, String>,
+        client_info: ClientInfo,
+    ) -> Result<(), McpRigIntegrationError> {
+        let mut transport = WebSocketTransport::new(url).with_headers(headers);
+        if let Some(provider) = self.auth_providers.get(&id) {
+            transport = transport.with_auth(Arc::clone(provider));
+        }
+        if let Some(sink) = self.notification_sink(&id) {
+            transport = transport.with_notifications(sink);
+        }
+        self.add_client(id, transport, client_info).await
+    }
+
+    /// Add a client using a UnixSocketTransport
+    #[cfg(unix)]
+    pub async fn add_unix_client(
+        &mut self,
+        id: String,
+        path: impl Into<PathBuf>,
+        client_info: ClientInfo,
+    ) -> Result<(), McpRigIntegrationError> {
+        let mut transport = UnixSocketTransport::new(path);
+        if let Some(sink) = self.notification_sink(&id) {
+            transport = transport.with_notifications(sink);
+        }
+        self.add_client(id, transport, client_info).await
+    }
+
+    /// Add a client using a TcpTransport
+    pub async fn add_tcp_client(
+        &mut self,
+        id: String,
+        address: &str,
+        client_info: ClientInfo,
+    ) -> Result<(), McpRigIntegrationError> {
+        let mut transport = TcpTransport::new(address);
+        if let Some(sink) = self.notification_sink(&id) {
+            transport = transport.with_notifications(sink);
+        }
+        self.add_client(id, transport, client_info).await
+    }
+
+    /// Add a client connected to a server running in this process.
+    ///
+    /// The server is driven by a background task over an in-process
+    /// channel, so no child process or socket is involved.
+    pub async fn mount_server(
+        &mut self,
+        id: String,
+        server: Arc<dyn McpServer>,
+        client_info: ClientInfo,
+    ) -> Result<(), McpRigIntegrationError> {
+        let (mut transport, channel) = ChannelTransport::pair(32);
+        if let Some(sink) = self.notification_sink(&id) {
+            transport = transport.with_notifications(sink);
+        }
+        tokio::spawn(channel.serve(server));
+        self.add_client(id, transport, client_info).await
+    }
+
+    /// Receive the notifications sent by the servers of clients added from now on.
+    ///
+    /// Each notification carries the ID of the client whose server sent it.
+    /// Subscribing again replaces the previous receiver for later clients.
+    /// Legacy SSE clients and clients added with `add_client` don't deliver
+    /// notifications; pass a `NotificationSink` to the transport instead.
+    pub fn subscribe_notifications(&mut self) -> mpsc::UnboundedReceiver<ServerNotification> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.notifications = Some(sender);
+        receiver
+    }
+
+    fn notification_sink(&self, id: &str) -> Option<NotificationSink> {
+        self.notifications
+            .as_ref()
+            .map(|sender| NotificationSink::new(id, sender.clone()))
+    }
+
+    /// Get the session of a Streamable HTTP client
+    pub fn get_http_session(&self, id: &str) -> Option<HttpSession> {
+        self.http_sessions.get(id).cloned()
+    }
+
+    /// Add a client for every server in a configuration file.
+    ///
+    /// Servers are connected in ID order and the first failure stops the
+    /// process. The server entries are kept so their per-tool settings can be
+    /// looked up later with `get_server_config`.
+    pub async fn add_clients_from_config(
+        &mut self,
+        config: &McpConfig,
+        client_info: ClientInfo,
+    ) -> Result<(), McpRigIntegrationError> {
+        for (id, server) in &config.servers {
+            // ClientInfo is not Clone
+            let info = ClientInfo {
+                name: client_info.name.clone(),
+                version: client_info.version.clone(),
+            };
+            self.add_server(id.clone(), server, info).await?;
+        }
+        Ok(())
+    }
+
+    /// Add a client described by a single config file entry
+    pub async fn add_server(
+        &mut self,
+        id: String,
+        server: &ServerConfig,
+        client_info: ClientInfo,
+    ) -> Result<(), McpRigIntegrationError> {
+        let missing = |field: &str| {
+            McpRigIntegrationError::ConfigError(format!("server '{}' is missing `{}`", id, field))
+        };
+
+        match server.transport()? {
+            TransportKind::Stdio => {
+                let command = server
+                    .command
+                    .as_deref()
+                    .ok_or_else(|| missing("command"))?;
+                let mut transport = ProcessTransport::new(command, server.args.clone())
+                    .with_envs(server.env.clone())
+                    .with_inherit_env(!server.clear_env)
+                    .with_limits(server.limits.unwrap_or_default());
+                if let Some(cwd) = &server.cwd {
+                    transport = transport.with_cwd(cwd);
+                }
+                self.add_process_client(id.clone(), transport, client_info)
+                    .await?;
+            }
+            TransportKind::Sse => {
+                let url = server.url.as_deref().ok_or_else(|| missing("url"))?;
+                self.add_sse_client(id.clone(), url, server.headers.clone(), client_info)
+                    .await?;
+            }
+            TransportKind::Http => {
+                let url = server.url.as_deref().ok_or_else(|| missing("url"))?;
+                self.add_http_client(id.clone(), url, server.headers.clone(), client_info)
+                    .await?;
+            }
+            TransportKind::WebSocket => {
+                let url = server.url.as_deref().ok_or_else(|| missing("url"))?;
+                self.add_ws_client(id.clone(), url, server.headers.clone(), client_info)
+                    .await?;
+            }
+            TransportKind::Unix => {
+                let url = server.url.as_deref().ok_or_else(|| missing("url"))?;
+                let path = url
+                    .strip_prefix("unix://")
+                    .or_else(|| url.strip_prefix("unix:"))
+                    .unwrap_or(url);
+                #[cfg(unix)]
+                self.add_unix_client(id.clone(), path, client_info).await?;
+                #[cfg(not(unix))]
+                return Err(McpRigIntegrationError::ConfigError(format!(
+                    "server '{}' uses a Unix socket ({}), which is not supported on this platform",
+                    id, path
+                )));
+            }
+            TransportKind::Tcp => {
+                let url = server.url.as_deref().ok_or_else(|| missing("url"))?;
+                let address = url.strip_prefix("tcp://").unwrap_or(url);
+                self.add_tcp_client(id.clone(), address, client_info)
+                    .await?;
+            }
+        }
+
+        self.server_configs.insert(id, server.clone());
+        Ok(())
+    }
+
     /// Generic method to add a client with any transport
     pub async fn add_client(
         &mut self,
@@ -135,6 +473,10 @@ impl McpConnectionManager {
             .await
             .map_err(|e| McpRigIntegrationError::McpError(e.to_string()))?;
 
+        // Track request IDs so in-flight tool calls can be cancelled
+        let handle = TrackedHandle::new(handle);
+        let canceller: Arc<dyn RequestCanceller> = Arc::new(handle.clone());
+
         let service = McpService::with_timeout(handle, self.timeout);
         let mut client = McpClient::new(service);
 
@@ -145,17 +487,209 @@ impl McpConnectionManager {
             .await
             .map_err(|e| McpRigIntegrationError::McpError(e.to_string()))?;
 
-        self.clients.insert(id, Arc::new(Box::new(client)));
+        let client = self.verify_tool_pins(&id, Arc::new(client)).await?;
+
+        self.cancellers.insert(id.clone(), canceller);
+        self.clients.insert(id, client);
         Ok(())
     }
 
+    /// Enable tool definition pinning with the lockfile at `path`.
+    ///
+    /// Every client added afterwards has its tools compared against the
+    /// lockfile when it connects. Servers without an entry are pinned on
+    /// first use; changed definitions are handled according to `mode`, and
+    /// the outcome is available from `pin_report`.
+    pub fn enable_tool_pinning(
+        &mut self,
+        path: impl Into<PathBuf>,
+        mode: PinMode,
+    ) -> Result<(), McpRigIntegrationError> {
+        let path = path.into();
+        let lockfile = ToolLockfile::load(&path)?;
+        let approver = self.pinning.take().and_then(|p| p.approver);
+        self.pinning = Some(ToolPinning {
+            path,
+            lockfile,
+            mode,
+            approver,
+        });
+        Ok(())
+    }
+
+    /// Set the handler that re-approves changed tools in `PinMode::RequireApproval`.
+    ///
+    /// Has no effect unless `enable_tool_pinning` was called first. Without a
+    /// handler, changed tools are blocked, and only `ApprovalDecision::Approve`
+    /// re-pins a tool; `Edit` is treated as a denial.
+    pub fn set_pin_approver(&mut self, approver: Arc<dyn ApprovalHandler>) {
+        if let Some(pinning) = self.pinning.as_mut() {
+            pinning.approver = Some(approver);
+        }
+    }
+
+    /// Get the result of checking a client's tools against the lockfile
+    pub fn pin_report(&self, id: &str) -> Option<&PinReport> {
+        self.pin_reports.get(id)
+    }
+
+    /// Compare a newly connected client's tools against the lockfile.
+    ///
+    /// Returns the client to register, wrapped so that later tool listings
+    /// are checked against the lockfile as well.
+    async fn verify_tool_pins(
+        &mut self,
+        id: &str,
+        client: Arc<dyn McpClientTrait>,
+    ) -> Result<Arc<dyn McpClientTrait>, McpRigIntegrationError> {
+        let Some(pinning) = self.pinning.as_mut() else {
+            return Ok(client);
+        };
+
+        let tools = list_all_tools(client.as_ref()).await?;
+        let mut report = pinning.lockfile.verify(id, &tools);
+
+        if report.first_use {
+            tracing::info!(
+                server = id,
+                tools = tools.len(),
+                "pinning tool definitions on first use"
+            );
+            pinning.lockfile.pin_all(id, &tools);
+            pinning.lockfile.save(&pinning.path)?;
+            self.pin_reports.insert(id.to_string(), report);
+            let pins = pinning
+                .lockfile
+                .servers
+                .get(id)
+                .cloned()
+                .unwrap_or_default();
+            return Ok(Arc::new(PinnedClient::new(
+                client,
+                id,
+                pins,
+                pinning.mode,
+                &tools,
+                HashSet::new(),
+            )));
+        }
+
+        for change in &report.changes {
+            tracing::warn!(
+                server = id,
+                "tool definition changed since it was pinned:\n{}",
+                change
+            );
+        }
+
+        let mut blocked = HashSet::new();
+        let mut lockfile_changed = false;
+        for change in &report.changes {
+            let name = change.tool_name();
+            let current = tools.iter().find(|t| t.name == name);
+            match (pinning.mode, change, current) {
+                (PinMode::Warn, _, _) => {}
+                (_, ToolChange::Removed { .. }, _) => {
+                    if pinning.mode == PinMode::RequireApproval {
+                        pinning.lockfile.unpin(id, name);
+                        lockfile_changed = true;
+                    }
+                }
+                (PinMode::Block, _, _) | (_, _, None) => {
+                    blocked.insert(name.to_string());
+                }
+                (PinMode::RequireApproval, _, Some(tool)) => {
+                    let approved = match &pinning.approver {
+                        Some(approver) => {
+                            let request = ApprovalRequest {
+                                tool_name: name.to_string(),
+                                exposed_name: name.to_string(),
+                                description: change.to_string(),
+                                arguments: json!({
+                                    "server": id,
+                                    "change": change.to_string(),
+                                }),
+                            };
+                            // Editing has no meaning for a definition change,
+                            // so only an explicit approval re-pins the tool
+                            matches!(approver.review(&request).await, ApprovalDecision::Approve)
+                        }
+                        None => false,
+                    };
+
+                    if approved {
+                        pinning.lockfile.pin(id, tool);
+                        lockfile_changed = true;
+                    } else {
+                        blocked.insert(name.to_string());
+                    }
+                }
+            }
+        }
+
+        if lockfile_changed {
+            pinning.lockfile.save(&pinning.path)?;
+        }
+
+        if !blocked.is_empty() {
+            tracing::warn!(server = id, blocked = ?blocked, "blocking tools with unapproved definition changes");
+        }
+        let pins = pinning
+            .lockfile
+            .servers
+            .get(id)
+            .cloned()
+            .unwrap_or_default();
+        let client: Arc<dyn McpClientTrait> = Arc::new(PinnedClient::new(
+            client,
+            id,
+            pins,
+            pinning.mode,
+            &tools,
+            blocked.clone(),
+        ));
+
+        report.blocked = blocked.into_iter().collect();
+        report.blocked.sort();
+        self.pin_reports.insert(id.to_string(), report);
+        Ok(client)
+    }
+
+    /// Register an already initialized client under the given ID.
+    ///
+    /// This is useful for clients that are not created through a transport,
+    /// such as `RecordingClient` and `ReplayClient`. An existing client with
+    /// the same ID is replaced.
+    pub fn insert_client(&mut self, id: String, client: Arc<dyn McpClientTrait>) {
+        self.cancellers.remove(&id);
+        self.clients.insert(id, client);
+    }
+
     /// Get a client by ID
-    pub fn get_client(&self, id: &str) -> Option<Arc<Box<dyn McpClientTrait>>> {
+    pub fn get_client(&self, id: &str) -> Option<Arc<dyn McpClientTrait>> {
         self.clients.get(id).cloned()
     }
 
+    /// Get the request canceller for a client by ID
+    ///
+    /// Only clients added through a transport have a canceller; clients
+    /// registered with `insert_client` do not.
+    pub fn get_canceller(&self, id: &str) -> Option<Arc<dyn RequestCanceller>> {
+        self.cancellers.get(id).cloned()
+    }
+
+    /// Get the config file entry a client was created from
+    pub fn get_server_config(&self, id: &str) -> Option<&ServerConfig> {
+        self.server_configs.get(id)
+    }
+
     /// Remove a client by ID
     pub fn remove_client(&mut self, id: &str) -> bool {
+        self.cancellers.remove(id);
+        self.server_configs.remove(id);
+        self.pin_reports.remove(id);
+        self.http_sessions.remove(id);
+        self.auth_providers.remove(id);
         self.clients.remove(id).is_some()
     }
 
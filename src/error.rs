@@ -39,6 +39,27 @@ pub enum McpRigIntegrationError {
     #[error("Tool execution error: {0}")]
     ToolExecutionError(String),
 
+    /// Errors in the transport connecting to an MCP server.
+    ///
+    /// These errors occur when a message cannot be delivered to or received
+    /// from the server, and are usually worth retrying.
+    #[error("Transport error: {0}")]
+    TransportError(String),
+
+    /// Errors that occur when a tool call exceeds its timeout.
+    ///
+    /// The call is cancelled before this error is returned, so the tool
+    /// may or may not have completed its work on the server.
+    #[error("Timeout: {0}")]
+    Timeout(String),
+
+    /// Errors that occur when two tools map to the same provider-safe name.
+    ///
+    /// These errors happen during registration when sanitized tool names
+    /// cannot be made unique.
+    #[error("Tool name collision: {0}")]
+    NameCollision(String),
+
     /// Errors that occur during initialization.
     ///
     /// These errors happen when setting up components, such as during
@@ -53,6 +74,28 @@ pub enum McpRigIntegrationError {
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
 
+    /// Errors that occur while loading, saving or replaying a cassette.
+    ///
+    /// These errors happen when a recorded MCP session cannot be read from
+    /// or written to disk.
+    #[error("Cassette error: {0}")]
+    CassetteError(String),
+
+    /// Errors in the configuration file.
+    ///
+    /// These errors occur when a config file cannot be read or contains an
+    /// invalid server entry.
+    #[error("Config error: {0}")]
+    ConfigError(String),
+
+    /// Errors that occur while authorizing with a remote server.
+    ///
+    /// These errors happen when a token cannot be obtained, refreshed or
+    /// stored, for example because the authorization server rejected the
+    /// client or the user did not complete the login in time.
+    #[error("Authorization error: {0}")]
+    AuthError(String),
+
     /// Any other errors that don't fit into the above categories.
     ///
     /// This is a catch-all for errors that aren't specifically handled
@@ -61,10 +104,26 @@ pub enum McpRigIntegrationError {
     Other(String),
 }
 
+impl McpRigIntegrationError {
+    /// Whether the error is likely to go away if the operation is retried.
+    ///
+    /// Transport failures and timeouts are transient; errors reported by the
+    /// server or the tool itself are not.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::TransportError(_) | Self::Timeout(_))
+    }
+}
+
 // Implement From for specific error types instead of a generic implementation
 impl From<mcp_client::Error> for McpRigIntegrationError {
     fn from(err: mcp_client::Error) -> Self {
-        Self::McpError(err.to_string())
+        match err {
+            mcp_client::Error::Transport(_) | mcp_client::Error::NotReady => {
+                Self::TransportError(err.to_string())
+            }
+            mcp_client::Error::Timeout(_) => Self::Timeout(err.to_string()),
+            _ => Self::McpError(err.to_string()),
+        }
     }
 }
 
@@ -75,10 +134,16 @@ impl PartialEq for McpRigIntegrationError {
             (Self::McpError(a), Self::McpError(b)) => a == b,
             (Self::RigError(a), Self::RigError(b)) => a == b,
             (Self::ToolExecutionError(a), Self::ToolExecutionError(b)) => a == b,
+            (Self::TransportError(a), Self::TransportError(b)) => a == b,
+            (Self::Timeout(a), Self::Timeout(b)) => a == b,
+            (Self::NameCollision(a), Self::NameCollision(b)) => a == b,
             (Self::InitError(a), Self::InitError(b)) => a == b,
             (Self::SerializationError(a), Self::SerializationError(b)) => {
                 a.to_string() == b.to_string()
             }
+            (Self::CassetteError(a), Self::CassetteError(b)) => a == b,
+            (Self::ConfigError(a), Self::ConfigError(b)) => a == b,
+            (Self::AuthError(a), Self::AuthError(b)) => a == b,
             (Self::Other(a), Self::Other(b)) => a == b,
             _ => false,
         }
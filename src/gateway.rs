@@ -0,0 +1,490 @@
+// src/gateway.rs
+
+//! One MCP server in front of many.
+//!
+//! `McpGateway` is an `McpServer` that offers the union of its upstream
+//! servers' tools, resources and prompts, so hosts only need to configure a
+//! single server. Tool and prompt names are prefixed with the server ID
+//! (`git__git_log`) and resource names likewise; resource URIs are kept and
+//! routed to the server that listed them.
+//!
+//! Tool calls go through `McpToolAdapter`s built from the config, so
+//! `allowTools` / `denyTools`, policies, per-tool overrides and output
+//! budgets are applied in one place. Tools that need approval are denied
+//! unless an approval handler is set, since a gateway has no one to ask.
+//!
+//! Upstream notifications are passed on to the client. List changes also
+//! clear the gateway's catalog so the next listing is fresh.
+
+use crate::adapter::{McpToolAdapter, McpToolArgs};
+use crate::approval::{ApprovalGate, ApprovalHandler, AutoDenyApprover};
+use crate::cancellation::RequestCanceller;
+use crate::config::McpConfig;
+use crate::connection::McpConnectionManager;
+use crate::naming::ToolNameMap;
+use crate::notifications::ServerNotification;
+use crate::pagination::list_all_prompts;
+use crate::server::{
+    invalid_params, method_not_found, negotiate_protocol_version, request_arguments, to_result,
+    McpServer,
+};
+use crate::toolset::list_all_tools;
+use mcp_client::client::McpClientTrait;
+use mcp_spec::protocol::{ErrorData, JsonRpcNotification, INTERNAL_ERROR, METHOD_NOT_FOUND};
+use rig::tool::Tool;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, RwLock};
+
+/// The default separator between server IDs and names.
+pub const DEFAULT_NAMESPACE_SEPARATOR: &str = "__";
+
+/// A server behind the gateway.
+struct Upstream {
+    /// The client ID, used as the namespace
+    id: String,
+    /// The connected client
+    client: Arc<dyn McpClientTrait>,
+    /// Cancels in-flight requests when a call times out
+    canceller: Option<Arc<dyn RequestCanceller>>,
+}
+
+/// What the upstream servers offer, built on first use.
+#[derive(Default)]
+struct Catalog {
+    /// Exposed tool names in listing order, with their adapters
+    tools: Option<Vec<(String, Arc<McpToolAdapter>)>>,
+    /// Exposed prompt names in listing order, with the upstream and original name
+    prompts: Option<Vec<(String, usize, String)>>,
+    /// Resource URIs in listing order, with the upstream that listed them
+    resources: Option<Vec<(String, usize)>>,
+}
+
+/// An MCP server aggregating other MCP servers.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let config = McpConfig::load("mcp.json")?;
+/// let mut manager = McpConnectionManager::new();
+/// let notifications = manager.subscribe_notifications();
+/// manager.add_clients_from_config(&config, client_info).await?;
+///
+/// let gateway = McpGateway::from_manager(&manager, config).with_notifications(notifications);
+/// mcp_rig::serve_stdio(Arc::new(gateway)).await?;
+/// ```
+pub struct McpGateway {
+    /// The server name reported to clients
+    name: String,
+    /// The server version reported to clients
+    version: String,
+    /// The servers behind the gateway, in namespace order
+    upstreams: Vec<Upstream>,
+    /// Allow/deny lists, per-tool settings and policies
+    config: McpConfig,
+    /// Goes between the server ID and the original name
+    separator: String,
+    /// Reviews calls to tools that need approval
+    approver: Arc<dyn ApprovalHandler>,
+    /// Lazily listed tools, prompts and resources
+    catalog: Arc<RwLock<Catalog>>,
+    /// Upstream notifications, taken when serving starts
+    notifications: Mutex<Option<mpsc::UnboundedReceiver<ServerNotification>>>,
+}
+
+impl McpGateway {
+    /// Create a gateway with no servers.
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            upstreams: Vec::new(),
+            config: McpConfig::default(),
+            separator: DEFAULT_NAMESPACE_SEPARATOR.to_string(),
+            approver: Arc::new(AutoDenyApprover::new(
+                "the tool needs approval, which the gateway cannot ask for",
+            )),
+            catalog: Arc::new(RwLock::new(Catalog::default())),
+            notifications: Mutex::new(None),
+        }
+    }
+
+    /// Create a gateway for every client of `manager`, configured by `config`.
+    ///
+    /// Servers are namespaced in client ID order.
+    pub fn from_manager(manager: &McpConnectionManager, config: McpConfig) -> Self {
+        let mut ids = manager.client_ids();
+        ids.sort();
+        let mut gateway =
+            Self::new("mcp-rig-gateway", env!("CARGO_PKG_VERSION")).with_config(config);
+        for id in ids {
+            if let Some(client) = manager.get_client(&id) {
+                let canceller = manager.get_canceller(&id);
+                gateway.upstreams.push(Upstream {
+                    id,
+                    client,
+                    canceller,
+                });
+            }
+        }
+        gateway
+    }
+
+    /// Add a server under the namespace `id`.
+    pub fn with_server(mut self, id: impl Into<String>, client: Arc<dyn McpClientTrait>) -> Self {
+        self.upstreams.push(Upstream {
+            id: id.into(),
+            client,
+            canceller: None,
+        });
+        self
+    }
+
+    /// Apply the allow/deny lists, per-tool settings and policies in `config`.
+    pub fn with_config(mut self, config: McpConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Set the separator between server IDs and names.
+    pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Review calls to tools that need approval with `handler`.
+    pub fn with_approval(mut self, handler: Arc<dyn ApprovalHandler>) -> Self {
+        self.approver = handler;
+        self
+    }
+
+    /// Pass on the upstream notifications received from `notifications`.
+    ///
+    /// Use the receiver from `McpConnectionManager::subscribe_notifications`.
+    pub fn with_notifications(
+        self,
+        notifications: mpsc::UnboundedReceiver<ServerNotification>,
+    ) -> Self {
+        *self.notifications.lock().unwrap() = Some(notifications);
+        self
+    }
+
+    fn namespaced(&self, server: &str, name: &str) -> String {
+        format!("{}{}{}", server, self.separator, name)
+    }
+
+    /// List every upstream's tools, keeping the allowed ones.
+    async fn load_tools(&self) -> Vec<(String, Arc<McpToolAdapter>)> {
+        let gate = ApprovalGate::new(Arc::clone(&self.approver));
+        let mut names = ToolNameMap::default();
+        let mut tools = Vec::new();
+
+        for upstream in &self.upstreams {
+            let listed = match list_all_tools(upstream.client.as_ref()).await {
+                Ok(listed) => listed,
+                Err(e) => {
+                    tracing::warn!(server = %upstream.id, error = %e, "failed to list tools, skipping server");
+                    continue;
+                }
+            };
+            let server = self.config.server(&upstream.id);
+            for tool in listed {
+                if server.is_some_and(|server| !server.allows_tool(&tool.name)) {
+                    tracing::debug!(server = %upstream.id, tool = %tool.name, "tool not allowed");
+                    continue;
+                }
+                let exposed = match names.insert(&self.namespaced(&upstream.id, &tool.name)) {
+                    Ok(exposed) => exposed,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "skipping tool");
+                        continue;
+                    }
+                };
+                let mut adapter = McpToolAdapter::new(
+                    Arc::clone(&upstream.client),
+                    tool.name,
+                    tool.description,
+                    tool.input_schema,
+                )
+                .with_exposed_name(exposed.clone())
+                .with_approval(gate.clone());
+                if let Some(canceller) = &upstream.canceller {
+                    adapter = adapter.with_canceller(Arc::clone(canceller));
+                }
+                let adapter = self.config.configure_adapter(&upstream.id, adapter);
+                tools.push((exposed, Arc::new(adapter)));
+            }
+        }
+        tools
+    }
+
+    async fn tools(&self) -> Vec<(String, Arc<McpToolAdapter>)> {
+        if let Some(tools) = &self.catalog.read().await.tools {
+            return tools.clone();
+        }
+        let tools = self.load_tools().await;
+        self.catalog.write().await.tools = Some(tools.clone());
+        tools
+    }
+
+    async fn prompts(&self) -> Vec<(String, usize, String)> {
+        if let Some(prompts) = &self.catalog.read().await.prompts {
+            return prompts.clone();
+        }
+        let mut prompts = Vec::new();
+        for (index, upstream) in self.upstreams.iter().enumerate() {
+            for prompt in list_prompts(upstream).await {
+                let exposed = self.namespaced(&upstream.id, &prompt.name);
+                prompts.push((exposed, index, prompt.name));
+            }
+        }
+        self.catalog.write().await.prompts = Some(prompts.clone());
+        prompts
+    }
+
+    async fn resources(&self) -> Vec<(String, usize)> {
+        if let Some(resources) = &self.catalog.read().await.resources {
+            return resources.clone();
+        }
+        let mut resources = Vec::new();
+        for (index, upstream) in self.upstreams.iter().enumerate() {
+            for resource in list_resources(upstream).await {
+                resources.push((resource.uri, index));
+            }
+        }
+        self.catalog.write().await.resources = Some(resources.clone());
+        resources
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        json!({
+            "protocolVersion": negotiate_protocol_version(params),
+            "capabilities": {
+                "tools": { "listChanged": true },
+                "prompts": { "listChanged": true },
+                "resources": { "listChanged": true }
+            },
+            "serverInfo": { "name": self.name, "version": self.version }
+        })
+    }
+
+    async fn list_tools(&self) -> Result<Value, ErrorData> {
+        let mut listed = Vec::new();
+        for (_, adapter) in self.tools().await {
+            let definition = adapter.definition(String::new()).await;
+            listed.push(mcp_spec::Tool::new(
+                definition.name,
+                definition.description,
+                definition.parameters,
+            ));
+        }
+        Ok(json!({ "tools": listed }))
+    }
+
+    async fn call_tool(&self, params: Value) -> Result<Value, ErrorData> {
+        let name = params["name"]
+            .as_str()
+            .ok_or_else(|| invalid_params("missing tool name"))?;
+        let adapter = self
+            .tools()
+            .await
+            .into_iter()
+            .find(|(exposed, _)| exposed == name)
+            .map(|(_, adapter)| adapter)
+            .ok_or_else(|| invalid_params(format!("Unknown tool: {}", name)))?;
+        let args = request_arguments(&params);
+
+        let result = match adapter.call(McpToolArgs { args }).await {
+            Ok(Value::Array(content)) => json!({ "content": content }),
+            Ok(Value::String(text)) => json!({ "content": [{ "type": "text", "text": text }] }),
+            Ok(other) => json!({ "content": [{ "type": "text", "text": other.to_string() }] }),
+            Err(e) => json!({
+                "content": [{ "type": "text", "text": e.to_string() }],
+                "isError": true
+            }),
+        };
+        Ok(result)
+    }
+
+    async fn list_prompts(&self) -> Result<Value, ErrorData> {
+        let mut listed = Vec::new();
+        let mut routes = Vec::new();
+        for (index, upstream) in self.upstreams.iter().enumerate() {
+            for mut prompt in list_prompts(upstream).await {
+                let exposed = self.namespaced(&upstream.id, &prompt.name);
+                let original = std::mem::replace(&mut prompt.name, exposed.clone());
+                routes.push((exposed, index, original));
+                listed.push(prompt);
+            }
+        }
+        // Keep the routing table in step with what the client saw
+        self.catalog.write().await.prompts = Some(routes);
+        Ok(json!({ "prompts": listed }))
+    }
+
+    async fn get_prompt(&self, params: Value) -> Result<Value, ErrorData> {
+        let name = params["name"]
+            .as_str()
+            .ok_or_else(|| invalid_params("missing prompt name"))?;
+        let (_, index, original) = self
+            .prompts()
+            .await
+            .into_iter()
+            .find(|(exposed, _, _)| exposed == name)
+            .ok_or_else(|| invalid_params(format!("Unknown prompt: {}", name)))?;
+        let arguments = request_arguments(&params);
+        let result = self.upstreams[index]
+            .client
+            .get_prompt(&original, arguments)
+            .await
+            .map_err(upstream_error)?;
+        to_result(result)
+    }
+
+    async fn list_resources(&self) -> Result<Value, ErrorData> {
+        let mut listed = Vec::new();
+        let mut routes = Vec::new();
+        for (index, upstream) in self.upstreams.iter().enumerate() {
+            for mut resource in list_resources(upstream).await {
+                routes.push((resource.uri.clone(), index));
+                resource.name = self.namespaced(&upstream.id, &resource.name);
+                listed.push(resource);
+            }
+        }
+        self.catalog.write().await.resources = Some(routes);
+        Ok(json!({ "resources": listed }))
+    }
+
+    async fn read_resource(&self, params: Value) -> Result<Value, ErrorData> {
+        let uri = params["uri"]
+            .as_str()
+            .ok_or_else(|| invalid_params("missing resource URI"))?;
+        // The first server listing a URI serves it
+        let (_, index) = self
+            .resources()
+            .await
+            .into_iter()
+            .find(|(listed, _)| listed == uri)
+            .ok_or_else(|| invalid_params(format!("Unknown resource: {}", uri)))?;
+        let result = self.upstreams[index]
+            .client
+            .read_resource(uri)
+            .await
+            .map_err(upstream_error)?;
+        to_result(result)
+    }
+}
+
+#[async_trait::async_trait]
+impl McpServer for McpGateway {
+    async fn handle_request(&self, method: &str, params: Value) -> Result<Value, ErrorData> {
+        match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => self.list_tools().await,
+            "tools/call" => self.call_tool(params).await,
+            "prompts/list" => self.list_prompts().await,
+            "prompts/get" => self.get_prompt(params).await,
+            "resources/list" => self.list_resources().await,
+            "resources/read" => self.read_resource(params).await,
+            _ => Err(method_not_found(method)),
+        }
+    }
+
+    fn take_notifications(&self) -> Option<mpsc::UnboundedReceiver<JsonRpcNotification>> {
+        let mut upstream = self.notifications.lock().unwrap().take()?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let catalog = Arc::clone(&self.catalog);
+        tokio::spawn(async move {
+            while let Some(notification) = upstream.recv().await {
+                let Some(forwarded) = forward(&catalog, notification).await else {
+                    continue;
+                };
+                if sender.send(forwarded).is_err() {
+                    break;
+                }
+            }
+        });
+        Some(receiver)
+    }
+}
+
+/// Update the catalog for an upstream notification and decide what the client sees.
+async fn forward(
+    catalog: &RwLock<Catalog>,
+    notification: ServerNotification,
+) -> Option<JsonRpcNotification> {
+    let ServerNotification {
+        client_id,
+        method,
+        mut params,
+    } = notification;
+    match method.as_str() {
+        "notifications/tools/list_changed" => catalog.write().await.tools = None,
+        "notifications/prompts/list_changed" => catalog.write().await.prompts = None,
+        "notifications/resources/list_changed" => catalog.write().await.resources = None,
+        // Tied to requests the gateway made, not the client's
+        "notifications/progress" | "notifications/cancelled" => return None,
+        "notifications/message" => {
+            // Say which server is logging
+            if let Value::Object(map) = &mut params {
+                map.entry("logger").or_insert_with(|| json!(client_id));
+            }
+        }
+        _ => {}
+    }
+    tracing::debug!(server = %client_id, method = %method, "forwarding notification");
+    Some(JsonRpcNotification {
+        jsonrpc: "2.0".to_string(),
+        method,
+        params: (!params.is_null()).then_some(params),
+    })
+}
+
+async fn list_prompts(upstream: &Upstream) -> Vec<mcp_spec::prompt::Prompt> {
+    match list_all_prompts(upstream.client.as_ref()).await {
+        Ok(prompts) => prompts,
+        // Servers without prompts say so with an error
+        Err(mcp_client::Error::RpcError { code, .. }) if code == METHOD_NOT_FOUND => Vec::new(),
+        Err(e) => {
+            tracing::warn!(server = %upstream.id, error = %e, "failed to list prompts, skipping server");
+            Vec::new()
+        }
+    }
+}
+
+async fn list_resources(upstream: &Upstream) -> Vec<mcp_spec::resource::Resource> {
+    let mut resources = Vec::new();
+    let mut cursor = None;
+    loop {
+        match upstream.client.list_resources(cursor).await {
+            Ok(page) => {
+                resources.extend(page.resources);
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => return resources,
+                }
+            }
+            Err(e) => {
+                tracing::warn!(server = %upstream.id, error = %e, "failed to list resources, skipping server");
+                return resources;
+            }
+        }
+    }
+}
+
+/// Pass on an upstream JSON-RPC error as is, and anything else as internal.
+fn upstream_error(e: mcp_client::Error) -> ErrorData {
+    match e {
+        mcp_client::Error::RpcError { code, message } => ErrorData {
+            code,
+            message,
+            data: None,
+        },
+        other => ErrorData {
+            code: INTERNAL_ERROR,
+            message: other.to_string(),
+            data: None,
+        },
+    }
+}
@@ -0,0 +1,416 @@
+// src/http_server.rs
+
+//! MCP servers served over Streamable HTTP.
+//!
+//! `serve_http` answers on a single endpoint, the way
+//! `StreamableHttpTransport` expects: an `initialize` POST starts a session
+//! whose ID comes back in the `Mcp-Session-Id` header, later POSTs must carry
+//! it, and a DELETE ends it. Replies are plain JSON, batched if the request
+//! was. A GET with the session header opens an SSE stream carrying the
+//! server's notifications.
+//!
+//! Requests from browsers are only accepted from allowed origins, so that a
+//! web page cannot reach a server on localhost through DNS rebinding. By
+//! default only loopback origins are allowed. `HttpServerOptions` can also
+//! require a bearer token and bounds how many sessions are kept, and for
+//! how long an idle one lives.
+
+use crate::error::McpRigIntegrationError;
+use crate::server::{dispatch, McpServer};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::post,
+    Json, Router,
+};
+use mcp_spec::protocol::{
+    ErrorData, JsonRpcError, JsonRpcMessage, JsonRpcNotification, INVALID_REQUEST, PARSE_ERROR,
+};
+use rand::Rng;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpListener, sync::broadcast};
+
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// How many notifications a slow SSE stream may fall behind before it skips some.
+const NOTIFICATION_BACKLOG: usize = 256;
+
+/// Access control and session limits for `serve_http_with`.
+///
+/// # Example
+///
+/// ```rust
+/// use mcp_rig::HttpServerOptions;
+/// use std::time::Duration;
+///
+/// let options = HttpServerOptions::default()
+///     .with_allowed_origin("https://app.example.com")
+///     .with_bearer_token("secret")
+///     .with_session_limits(64, Duration::from_secs(600));
+/// assert_eq!(options.max_sessions, 64);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpServerOptions {
+    /// Origins allowed besides loopback ones, such as `https://app.example.com`
+    pub allowed_origins: Vec<String>,
+    /// Token clients must send as `Authorization: Bearer <token>`
+    pub bearer_token: Option<String>,
+    /// Maximum number of open sessions; the least recently used is dropped
+    pub max_sessions: usize,
+    /// How long a session may go unused before it expires
+    pub session_ttl: Duration,
+}
+
+impl Default for HttpServerOptions {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            bearer_token: None,
+            max_sessions: 1024,
+            session_ttl: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl HttpServerOptions {
+    /// Allow requests from a non-loopback origin.
+    pub fn with_allowed_origin(mut self, origin: impl Into<String>) -> Self {
+        self.allowed_origins.push(origin.into());
+        self
+    }
+
+    /// Require clients to send this bearer token.
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.bearer_token = Some(token.into());
+        self
+    }
+
+    /// Set the session cap and idle lifetime.
+    pub fn with_session_limits(mut self, max_sessions: usize, ttl: Duration) -> Self {
+        self.max_sessions = max_sessions.max(1);
+        self.session_ttl = ttl;
+        self
+    }
+
+    /// Check whether a request's `Origin` header is allowed.
+    ///
+    /// Requests without one come from non-browser clients and are allowed.
+    fn allows_origin(&self, headers: &HeaderMap) -> bool {
+        let Some(origin) = headers.get(header::ORIGIN) else {
+            return true;
+        };
+        let Ok(origin) = origin.to_str() else {
+            return false;
+        };
+        if self
+            .allowed_origins
+            .iter()
+            .any(|allowed| allowed.trim_end_matches('/') == origin)
+        {
+            return true;
+        }
+        let Ok(url) = url::Url::parse(origin) else {
+            return false;
+        };
+        match url.host() {
+            Some(url::Host::Domain(domain)) => domain == "localhost",
+            Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+            Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+            None => false,
+        }
+    }
+
+    /// Check the request's bearer token, if one is required.
+    fn authorizes(&self, headers: &HeaderMap) -> bool {
+        let Some(token) = &self.bearer_token else {
+            return true;
+        };
+        let sent = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        // Compare in constant time so the token can't be guessed byte by byte
+        sent.len() == token.len()
+            && sent
+                .bytes()
+                .zip(token.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+struct HttpState {
+    server: Arc<dyn McpServer>,
+    options: HttpServerOptions,
+    /// Sessions started and not yet ended, with when they were last used
+    sessions: Mutex<HashMap<String, Instant>>,
+    /// The server's notifications, fanned out to every open SSE stream
+    notifications: broadcast::Sender<JsonRpcNotification>,
+}
+
+/// Serve `server` at `path` on `listener` until the listener fails.
+///
+/// Uses the default `HttpServerOptions`: loopback origins only, no token.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let listener = tokio::net::TcpListener::bind("127.0.0.1:8080").await?;
+/// mcp_rig::serve_http(Arc::new(server), listener, "/mcp").await?;
+/// ```
+pub async fn serve_http(
+    server: Arc<dyn McpServer>,
+    listener: TcpListener,
+    path: &str,
+) -> Result<(), McpRigIntegrationError> {
+    serve_http_with(server, listener, path, HttpServerOptions::default()).await
+}
+
+/// Serve `server` at `path` on `listener` with the given access options.
+pub async fn serve_http_with(
+    server: Arc<dyn McpServer>,
+    listener: TcpListener,
+    path: &str,
+    options: HttpServerOptions,
+) -> Result<(), McpRigIntegrationError> {
+    let (notifications, _) = broadcast::channel(NOTIFICATION_BACKLOG);
+    if let Some(mut incoming) = server.take_notifications() {
+        let fan_out = notifications.clone();
+        tokio::spawn(async move {
+            while let Some(notification) = incoming.recv().await {
+                // Nobody may be listening yet
+                let _ = fan_out.send(notification);
+            }
+        });
+    }
+
+    let state = Arc::new(HttpState {
+        server,
+        options,
+        sessions: Mutex::new(HashMap::new()),
+        notifications,
+    });
+    let router = Router::new()
+        .route(
+            path,
+            post(handle_post).get(handle_get).delete(handle_delete),
+        )
+        .with_state(state);
+    axum::serve(listener, router)
+        .await
+        .map_err(|e| McpRigIntegrationError::TransportError(e.to_string()))
+}
+
+async fn handle_post(
+    State(state): State<Arc<HttpState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Err(response) = check_access(&state, &headers) {
+        return *response;
+    }
+    let parsed = serde_json::from_slice::<Value>(&body).and_then(|body| match body {
+        Value::Array(items) => items
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<Vec<JsonRpcMessage>, _>>()
+            .map(|messages| (messages, true)),
+        single => serde_json::from_value(single).map(|message| (vec![message], false)),
+    });
+    let (messages, batch) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            let message = format!("Parse error: {}", e);
+            return rpc_error(StatusCode::BAD_REQUEST, PARSE_ERROR, message);
+        }
+    };
+
+    let initializing = messages
+        .iter()
+        .any(|m| matches!(m, JsonRpcMessage::Request(r) if r.method == "initialize"));
+    let session = if initializing {
+        start_session(&state)
+    } else {
+        match check_session(&state, &headers) {
+            Ok(id) => id,
+            Err(response) => return *response,
+        }
+    };
+
+    let mut replies = Vec::new();
+    for message in messages {
+        match message {
+            JsonRpcMessage::Request(request) => {
+                replies.push(dispatch(state.server.as_ref(), request));
+            }
+            JsonRpcMessage::Notification(notification) => {
+                let params = notification.params.unwrap_or_default();
+                state
+                    .server
+                    .handle_notification(&notification.method, params)
+                    .await;
+            }
+            other => tracing::debug!(message = ?other, "ignoring client message"),
+        }
+    }
+    if replies.is_empty() {
+        return StatusCode::ACCEPTED.into_response();
+    }
+
+    let mut replies = futures::future::join_all(replies).await;
+    let mut response = if batch {
+        Json(replies).into_response()
+    } else {
+        Json(replies.remove(0)).into_response()
+    };
+    if let Ok(value) = HeaderValue::from_str(&session) {
+        response.headers_mut().insert(SESSION_HEADER, value);
+    }
+    response
+}
+
+/// Open an SSE stream of the server's notifications.
+async fn handle_get(State(state): State<Arc<HttpState>>, headers: HeaderMap) -> Response {
+    if let Err(response) = check_access(&state, &headers) {
+        return *response;
+    }
+    if let Err(response) = check_session(&state, &headers) {
+        return *response;
+    }
+    let receiver = state.notifications.subscribe();
+    let events = futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(notification) => {
+                    let message = JsonRpcMessage::Notification(notification);
+                    let data = serde_json::to_string(&message).unwrap_or_default();
+                    let event = Event::default().event("message").data(data);
+                    return Some((Ok::<_, Infallible>(event), receiver));
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "SSE stream fell behind, skipping notifications");
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// End a session.
+async fn handle_delete(State(state): State<Arc<HttpState>>, headers: HeaderMap) -> Response {
+    if let Err(response) = check_access(&state, &headers) {
+        return *response;
+    }
+    match check_session(&state, &headers) {
+        Ok(id) => {
+            state.sessions.lock().unwrap().remove(&id);
+            StatusCode::OK.into_response()
+        }
+        Err(response) => *response,
+    }
+}
+
+/// Reject requests from disallowed origins or without the bearer token.
+fn check_access(state: &HttpState, headers: &HeaderMap) -> Result<(), Box<Response>> {
+    if !state.options.allows_origin(headers) {
+        tracing::warn!(origin = ?headers.get(header::ORIGIN), "rejecting request from disallowed origin");
+        return Err(Box::new(rpc_error(
+            StatusCode::FORBIDDEN,
+            INVALID_REQUEST,
+            "Origin not allowed".to_string(),
+        )));
+    }
+    if !state.options.authorizes(headers) {
+        let mut response = rpc_error(
+            StatusCode::UNAUTHORIZED,
+            INVALID_REQUEST,
+            "Missing or invalid bearer token".to_string(),
+        );
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        return Err(Box::new(response));
+    }
+    Ok(())
+}
+
+/// Start a session, dropping expired ones and the least recently used if full.
+fn start_session(state: &HttpState) -> String {
+    let id = format!("{:032x}", rand::thread_rng().gen::<u128>());
+    let mut sessions = state.sessions.lock().unwrap();
+    let now = Instant::now();
+    sessions.retain(|_, last_used| now.duration_since(*last_used) < state.options.session_ttl);
+    while sessions.len() >= state.options.max_sessions {
+        let oldest = sessions
+            .iter()
+            .min_by_key(|(_, last_used)| **last_used)
+            .map(|(id, _)| id.clone());
+        match oldest {
+            Some(oldest) => sessions.remove(&oldest),
+            None => break,
+        };
+    }
+    sessions.insert(id.clone(), now);
+    id
+}
+
+/// Get the request's session ID if the session exists and has not expired.
+fn check_session(state: &HttpState, headers: &HeaderMap) -> Result<String, Box<Response>> {
+    let Some(id) = headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) else {
+        return Err(Box::new(rpc_error(
+            StatusCode::BAD_REQUEST,
+            INVALID_REQUEST,
+            "Missing Mcp-Session-Id header".to_string(),
+        )));
+    };
+    let mut sessions = state.sessions.lock().unwrap();
+    let now = Instant::now();
+    let live = match sessions.get_mut(id) {
+        Some(last_used) if now.duration_since(*last_used) < state.options.session_ttl => {
+            *last_used = now;
+            true
+        }
+        Some(_) => {
+            sessions.remove(id);
+            false
+        }
+        None => false,
+    };
+    if !live {
+        // Clients start a new session when they see 404
+        return Err(Box::new(rpc_error(
+            StatusCode::NOT_FOUND,
+            INVALID_REQUEST,
+            "Unknown session".to_string(),
+        )));
+    }
+    Ok(id.to_string())
+}
+
+fn rpc_error(status: StatusCode, code: i32, message: String) -> Response {
+    let error = JsonRpcMessage::Error(JsonRpcError {
+        jsonrpc: "2.0".to_string(),
+        id: None,
+        error: ErrorData {
+            code,
+            message,
+            data: None,
+        },
+    });
+    (status, Json(error)).into_response()
+}
@@ -10,28 +10,103 @@
 //! making them directly usable within Rig's agent framework. This allows you to:
 //!
 //! - Expose existing MCP tools to LLM agents
-//! - Use tools with different transport mechanisms (stdio, SSE)
+//! - Use tools with different transport mechanisms (stdio, SSE, Streamable HTTP, WebSocket,
+//!   in-process)
 //! - Enable semantic retrieval of tools based on natural language queries
 //! - Manage multiple MCP clients in a single application
+//! - Serve Rig tools and agents to other MCP hosts with `RigServer`
+//! - Put many MCP servers behind one endpoint with `McpGateway`
 
 use rig::{agent::Agent, completion::CompletionModel};
 
 mod adapter;
+mod approval;
+mod auth;
+mod cache;
+mod cancellation;
+mod cassette;
+mod chat;
+mod config;
 mod connection;
 mod error;
+mod gateway;
+mod glob;
+mod http_server;
+mod inspection;
+mod lockfile;
+mod naming;
+mod notifications;
+mod overrides;
+mod pagination;
+mod policy;
+mod retry;
+mod rig_server;
+mod schema;
+mod server;
 mod toolset;
+mod transport;
+mod truncation;
+mod validation;
 
 pub use adapter::{McpToolAdapter, McpToolArgs, McpToolState};
+pub use approval::{
+    ApprovalDecision, ApprovalGate, ApprovalHandler, ApprovalRequest, AutoDenyApprover,
+    StdinApprover,
+};
+pub use auth::{
+    AuthProvider, BrowserOpener, FileTokenStore, MemoryTokenStore, OAuthProvider, OAuthToken,
+    StaticAuth, TokenStore,
+};
+pub use cache::ResultCache;
+pub use cancellation::RequestCanceller;
+pub use cassette::{
+    normalize_arguments, Cassette, Interaction, MismatchMode, RecordedErrorKind, RecordedResponse,
+    RecordingClient, ReplayClient,
+};
+pub use chat::{ChatEvent, ChatSession, Transcript};
+pub use config::{McpConfig, ServerConfig, ToolConfig, TransportKind};
 pub use connection::McpConnectionManager;
 pub use error::McpRigIntegrationError;
-pub use toolset::{create_mcp_toolset, register_mcp_tools};
+pub use gateway::{McpGateway, DEFAULT_NAMESPACE_SEPARATOR};
+pub use http_server::{serve_http, serve_http_with, HttpServerOptions};
+pub use inspection::{
+    Finding, FindingKind, HeuristicInspector, InspectionAction, OutputGuard, OutputInspector,
+};
+pub use lockfile::{tool_hash, PinMode, PinReport, PinnedTool, ToolChange, ToolLockfile};
+pub use naming::{sanitize_tool_name, ToolNameMap, DEFAULT_MAX_TOOL_NAME_LEN};
+pub use notifications::{NotificationSink, ServerNotification};
+pub use overrides::ToolOverride;
+pub use pagination::list_all_prompts;
+pub use policy::{PolicyCondition, PolicyDecision, PolicyEngine, PolicyRule};
+pub use retry::RetryPolicy;
+pub use rig_server::RigServer;
+pub use schema::{normalize_schema, NormalizedSchema, SchemaProfile};
+pub use server::{
+    invalid_params, method_not_found, negotiate_protocol_version, serve_stdio, serve_stream,
+    McpServer, PROTOCOL_VERSION, SUPPORTED_PROTOCOL_VERSIONS,
+};
+pub use toolset::{
+    create_mcp_toolset, create_mcp_toolset_with, register_mcp_tools, register_mcp_tools_with,
+};
+#[cfg(unix)]
+pub use transport::UnixSocketTransport;
+pub use transport::{
+    ChannelHandle, ChannelTransport, HttpSession, ProcessHandle, ProcessTransport, ResourceLimits,
+    ServerChannel, SocketHandle, StreamableHttpHandle, StreamableHttpTransport, TcpTransport,
+    WebSocketHandle, WebSocketTransport,
+};
+pub use truncation::{
+    BudgetUnit, OutputBudget, OutputStash, ReadOutputArgs, ReadOutputTool, StashPage,
+    TruncationStrategy,
+};
+pub use validation::{coerce, validate, ValidationIssue, ValidationMode};
 
 // Re-export relevant dependencies for ease of use
 pub use mcp_client;
 
 // High-level integration function that sets up a Rig agent with MCP tools
 pub async fn setup_rig_with_mcp(
-    mcp_client: std::sync::Arc<Box<dyn mcp_client::McpClientTrait>>,
+    mcp_client: std::sync::Arc<dyn mcp_client::McpClientTrait>,
     mut agent_builder: rig::agent::AgentBuilder<rig::providers::openai::CompletionModel>,
     model: rig::providers::openai::CompletionModel,
 ) -> Result<Agent<impl CompletionModel>, error::McpRigIntegrationError> {
@@ -50,7 +125,7 @@ pub async fn setup_rig_with_mcp(
 
 // /// Variant that also adds dynamic RAG-enabled tools
 // pub async fn setup_rig_with_mcp_rag(
-//     mcp_client: std::sync::Arc<Box<dyn mcp_client::McpClientTrait>>,
+//     mcp_client: std::sync::Arc<dyn mcp_client::McpClientTrait>,
 //     rig_client: &RigClient,
 //     model: &str,
 //     embedding_model: &str,
@@ -0,0 +1,452 @@
+// src/lockfile.rs
+
+//! Tool definition pinning and change detection.
+//!
+//! A compromised or updated MCP server can change a tool's description or
+//! schema between sessions, for example to smuggle instructions into the
+//! model context ("rug pull"). The lockfile records a hash of each tool's
+//! name, description and schema per server. When a client connects, the
+//! connection manager compares the server's tools against the lockfile and,
+//! depending on the `PinMode`, warns, blocks changed tools or asks for
+//! re-approval, reporting a diff of what changed.
+//!
+//! The check is repeated on every later `tools/list`, so a server cannot
+//! swap a definition after the connect-time check either.
+//!
+//! Servers that have no entry yet are pinned on first use.
+
+use crate::error::McpRigIntegrationError;
+use mcp_client::{
+    client::{ClientCapabilities, ClientInfo},
+    Error as McpClientError, McpClientTrait,
+};
+use mcp_spec::{
+    protocol::{
+        CallToolResult, GetPromptResult, InitializeResult, ListPromptsResult, ListResourcesResult,
+        ListToolsResult, ReadResourceResult,
+    },
+    Tool,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// Current lockfile format version.
+const LOCKFILE_VERSION: u32 = 1;
+
+/// How the connection manager reacts to changed tool definitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PinMode {
+    /// Log the changes and keep using the tools
+    #[default]
+    Warn,
+    /// Hide changed and new tools from the agent
+    Block,
+    /// Ask the approval handler; rejected tools are blocked
+    RequireApproval,
+}
+
+/// The recorded definition of a single tool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinnedTool {
+    /// SHA-256 of the canonical tool definition
+    pub hash: String,
+    /// The description at the time of pinning, kept for diffs
+    pub description: String,
+    /// The input schema at the time of pinning, kept for diffs
+    pub input_schema: Value,
+}
+
+impl PinnedTool {
+    /// Pin the current definition of a tool.
+    pub fn from_tool(tool: &Tool) -> Self {
+        Self {
+            hash: tool_hash(tool),
+            description: tool.description.clone(),
+            input_schema: tool.input_schema.clone(),
+        }
+    }
+}
+
+/// A difference between the pinned and the current tool definitions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolChange {
+    /// The server offers a tool that was not pinned
+    Added { name: String },
+    /// A pinned tool is no longer offered
+    Removed { name: String },
+    /// A pinned tool's description or schema changed
+    Modified { name: String, diff: Vec<String> },
+}
+
+impl ToolChange {
+    /// Get the name of the changed tool
+    pub fn tool_name(&self) -> &str {
+        match self {
+            Self::Added { name } | Self::Removed { name } | Self::Modified { name, .. } => name,
+        }
+    }
+}
+
+impl fmt::Display for ToolChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added { name } => write!(f, "+ {} (new tool)", name),
+            Self::Removed { name } => write!(f, "- {} (removed)", name),
+            Self::Modified { name, diff } => {
+                write!(f, "~ {}", name)?;
+                for line in diff {
+                    write!(f, "\n    {}", line)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The result of comparing a server's tools against the lockfile.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PinReport {
+    /// The server the report is for
+    pub server: String,
+    /// Whether the server had no entry and was pinned on first use
+    pub first_use: bool,
+    /// Differences from the pinned definitions
+    pub changes: Vec<ToolChange>,
+    /// Tools hidden from the agent because of unapproved changes
+    pub blocked: Vec<String>,
+}
+
+impl PinReport {
+    /// Check whether any tool differs from its pinned definition
+    pub fn has_changes(&self) -> bool {
+        !self.changes.is_empty()
+    }
+}
+
+/// Hash a tool's name, description and schema.
+///
+/// The schema is serialized with sorted keys so that the hash does not
+/// depend on the order in which the server emits object members.
+pub fn tool_hash(tool: &Tool) -> String {
+    let canonical = crate::cassette::normalize_arguments(&json!({
+        "name": tool.name,
+        "description": tool.description,
+        "inputSchema": tool.input_schema,
+    }));
+    let digest = Sha256::digest(canonical.to_string().as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Describe the paths at which two JSON values differ.
+fn diff_values(old: &Value, new: &Value, path: &str, out: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            let keys: std::collections::BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+            for key in keys {
+                let child = format!("{}/{}", path, key);
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => diff_values(x, y, &child, out),
+                    (Some(x), None) => out.push(format!("schema{}: removed (was {})", child, x)),
+                    (None, Some(y)) => out.push(format!("schema{}: added {}", child, y)),
+                    (None, None) => {}
+                }
+            }
+        }
+        (a, b) if a != b => out.push(format!(
+            "schema{}: {} -> {}",
+            if path.is_empty() { "/" } else { path },
+            a,
+            b
+        )),
+        _ => {}
+    }
+}
+
+/// Per-server record of pinned tool definitions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolLockfile {
+    /// Lockfile format version
+    pub version: u32,
+    /// Pinned tools keyed by server ID, then tool name
+    pub servers: BTreeMap<String, BTreeMap<String, PinnedTool>>,
+}
+
+impl Default for ToolLockfile {
+    fn default() -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            servers: BTreeMap::new(),
+        }
+    }
+}
+
+impl ToolLockfile {
+    /// Load a lockfile, returning an empty one if the file does not exist.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, McpRigIntegrationError> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(McpRigIntegrationError::ConfigError(format!(
+                "failed to read lockfile {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    /// Write the lockfile to disk.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), McpRigIntegrationError> {
+        let path = path.as_ref();
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, contents).map_err(|e| {
+            McpRigIntegrationError::ConfigError(format!(
+                "failed to write lockfile {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Check whether a server has pinned tools
+    pub fn contains_server(&self, server: &str) -> bool {
+        self.servers.contains_key(server)
+    }
+
+    /// Compare a server's current tools against the pinned definitions.
+    pub fn verify(&self, server: &str, tools: &[Tool]) -> PinReport {
+        let mut report = PinReport {
+            server: server.to_string(),
+            ..PinReport::default()
+        };
+        let Some(pinned) = self.servers.get(server) else {
+            report.first_use = true;
+            return report;
+        };
+
+        for tool in tools {
+            match pinned.get(&tool.name) {
+                None => report.changes.push(ToolChange::Added {
+                    name: tool.name.clone(),
+                }),
+                Some(pin) if pin.hash != tool_hash(tool) => {
+                    let mut diff = Vec::new();
+                    if pin.description != tool.description {
+                        diff.push(format!(
+                            "description: {:?} -> {:?}",
+                            pin.description, tool.description
+                        ));
+                    }
+                    diff_values(&pin.input_schema, &tool.input_schema, "", &mut diff);
+                    report.changes.push(ToolChange::Modified {
+                        name: tool.name.clone(),
+                        diff,
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+
+        let current: HashSet<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+        for name in pinned.keys() {
+            if !current.contains(name.as_str()) {
+                report
+                    .changes
+                    .push(ToolChange::Removed { name: name.clone() });
+            }
+        }
+
+        report
+    }
+
+    /// Record the current definition of a tool.
+    pub fn pin(&mut self, server: &str, tool: &Tool) {
+        self.servers
+            .entry(server.to_string())
+            .or_default()
+            .insert(tool.name.clone(), PinnedTool::from_tool(tool));
+    }
+
+    /// Replace a server's pins with its current tools.
+    pub fn pin_all(&mut self, server: &str, tools: &[Tool]) {
+        let pins = tools
+            .iter()
+            .map(|tool| (tool.name.clone(), PinnedTool::from_tool(tool)))
+            .collect();
+        self.servers.insert(server.to_string(), pins);
+    }
+
+    /// Remove the pin for a tool that the server no longer offers.
+    pub fn unpin(&mut self, server: &str, tool_name: &str) {
+        if let Some(pins) = self.servers.get_mut(server) {
+            pins.remove(tool_name);
+        }
+    }
+}
+
+/// Client wrapper that checks tool listings against the pinned definitions.
+///
+/// Tools blocked at connect time are hidden. Every later `tools/list` page is
+/// hashed against the pins again: in `PinMode::Warn` a changed or new tool
+/// is logged and kept, otherwise it is hidden and calls to it are refused
+/// for the rest of the session. Outside `PinMode::Warn`, only tools whose
+/// listed definition matched their pin can be called.
+pub(crate) struct PinnedClient {
+    inner: Arc<dyn McpClientTrait>,
+    server: String,
+    pins: BTreeMap<String, PinnedTool>,
+    mode: PinMode,
+    blocked: Mutex<HashSet<String>>,
+    verified: Mutex<HashSet<String>>,
+}
+
+impl PinnedClient {
+    pub(crate) fn new(
+        inner: Arc<dyn McpClientTrait>,
+        server: &str,
+        pins: BTreeMap<String, PinnedTool>,
+        mode: PinMode,
+        tools: &[Tool],
+        blocked: HashSet<String>,
+    ) -> Self {
+        let verified = tools
+            .iter()
+            .filter(|tool| !blocked.contains(&tool.name))
+            .filter(|tool| {
+                pins.get(&tool.name)
+                    .is_some_and(|pin| pin.hash == tool_hash(tool))
+            })
+            .map(|tool| tool.name.clone())
+            .collect();
+        Self {
+            inner,
+            server: server.to_string(),
+            pins,
+            mode,
+            blocked: Mutex::new(blocked),
+            verified: Mutex::new(verified),
+        }
+    }
+
+    /// Check whether a listed tool still matches its pinned definition.
+    fn is_allowed(&self, tool: &Tool) -> bool {
+        let mut blocked = self.blocked.lock().unwrap();
+        if blocked.contains(&tool.name) {
+            return false;
+        }
+        let matches = self
+            .pins
+            .get(&tool.name)
+            .is_some_and(|pin| pin.hash == tool_hash(tool));
+        if matches {
+            self.verified.lock().unwrap().insert(tool.name.clone());
+            return true;
+        }
+        self.verified.lock().unwrap().remove(&tool.name);
+
+        let change = match self.pins.get(&tool.name) {
+            Some(_) => "changed since it was pinned",
+            None => "is not pinned",
+        };
+        if self.mode == PinMode::Warn {
+            tracing::warn!(
+                server = %self.server,
+                tool = %tool.name,
+                "tool definition {}",
+                change
+            );
+            true
+        } else {
+            tracing::warn!(
+                server = %self.server,
+                tool = %tool.name,
+                "blocking tool whose definition {}",
+                change
+            );
+            blocked.insert(tool.name.clone());
+            false
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl McpClientTrait for PinnedClient {
+    async fn initialize(
+        &mut self,
+        _info: ClientInfo,
+        _capabilities: ClientCapabilities,
+    ) -> Result<InitializeResult, McpClientError> {
+        Err(McpClientError::UnexpectedResponse(
+            "PinnedClient wraps an already initialized client".to_string(),
+        ))
+    }
+
+    async fn list_resources(
+        &self,
+        next_cursor: Option<String>,
+    ) -> Result<ListResourcesResult, McpClientError> {
+        self.inner.list_resources(next_cursor).await
+    }
+
+    async fn read_resource(&self, uri: &str) -> Result<ReadResourceResult, McpClientError> {
+        self.inner.read_resource(uri).await
+    }
+
+    async fn list_tools(
+        &self,
+        next_cursor: Option<String>,
+    ) -> Result<ListToolsResult, McpClientError> {
+        let mut result = self.inner.list_tools(next_cursor).await?;
+        result.tools.retain(|tool| self.is_allowed(tool));
+        Ok(result)
+    }
+
+    async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<CallToolResult, McpClientError> {
+        if self.blocked.lock().unwrap().contains(name) {
+            return Err(McpClientError::UnexpectedResponse(format!(
+                "tool '{}' is blocked because its definition changed since it was pinned",
+                name
+            )));
+        }
+        if self.mode != PinMode::Warn && !self.verified.lock().unwrap().contains(name) {
+            let reason = if self.pins.contains_key(name) {
+                "its definition has not been verified against the lockfile"
+            } else {
+                "it is not pinned in the lockfile"
+            };
+            return Err(McpClientError::UnexpectedResponse(format!(
+                "tool '{}' is blocked because {}",
+                name, reason
+            )));
+        }
+        self.inner.call_tool(name, arguments).await
+    }
+
+    async fn list_prompts(
+        &self,
+        next_cursor: Option<String>,
+    ) -> Result<ListPromptsResult, McpClientError> {
+        self.inner.list_prompts(next_cursor).await
+    }
+
+    async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<GetPromptResult, McpClientError> {
+        self.inner.get_prompt(name, arguments).await
+    }
+}
@@ -1,105 +1,848 @@
 // src/main.rs
-//
-// This file contains an example application that demonstrates
-// the integration of MCP clients with Rig tools.
-
-use mcp_client::client::ClientInfo;
-use mcp_rig::McpConnectionManager;
-use std::collections::HashMap;
-use std::time::Duration;
+
+//! `mcp-rig` command-line interface.
+//!
+//! Connects to the servers in an MCP config file to list and inspect their
+//! tools, resources and prompts, and to call tools from the terminal. Tool
+//! calls go through the same adapters an agent would use, so the config's
+//! per-tool settings, policies and output budgets apply.
+//!
+//! Output is meant for people by default; pass `--json` for scripting.
+//!
+//! `mcp-rig chat` starts an interactive conversation with a model that can
+//! use the configured servers' tools, showing each call and its result.
+//! `mcp-rig serve` offers the same agent to other MCP hosts as a tool on
+//! stdio. `mcp-rig gateway` offers the servers themselves as one MCP server,
+//! on stdio or HTTP.
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use mcp_client::client::{ClientInfo, McpClientTrait};
+use mcp_rig::{
+    list_all_prompts, register_mcp_tools_with, serve_http_with, serve_stdio, ApprovalGate,
+    ApprovalHandler, AutoDenyApprover, ChatEvent, ChatSession, HttpServerOptions, McpConfig,
+    McpConnectionManager, McpGateway, McpToolAdapter, McpToolArgs, RigServer, ServerConfig,
+    StdinApprover, ToolNameMap, Transcript,
+};
+use mcp_spec::protocol::METHOD_NOT_FOUND;
+use rig::{
+    agent::{Agent, AgentBuilder},
+    completion::CompletionModel,
+    providers,
+    tool::Tool,
+};
+use serde_json::{json, Value};
+use std::{
+    io::Write,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::io::{AsyncBufReadExt, BufReader, Stdin};
+use tokio::sync::Mutex;
 use tracing_subscriber::EnvFilter;
 
+/// How much of a tool result is shown inline during a chat.
+const CHAT_RESULT_PREVIEW_CHARS: usize = 400;
+
+#[derive(Parser)]
+#[command(
+    name = "mcp-rig",
+    version,
+    about = "Inspect and call MCP servers from a config file"
+)]
+struct Cli {
+    /// Path to the MCP config file
+    #[arg(
+        long,
+        short,
+        global = true,
+        env = "MCP_RIG_CONFIG",
+        default_value = "mcp.json"
+    )]
+    config: PathBuf,
+
+    /// Print machine-readable JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Timeout for requests to the servers, in seconds
+    #[arg(long, global = true, default_value_t = 30)]
+    timeout: u64,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the configured servers
+    Servers,
+    /// Show the tools of a server with their descriptions and schemas
+    Tools {
+        /// Server ID from the config file
+        server: String,
+    },
+    /// Call a tool and print its result
+    Call {
+        /// Server ID from the config file
+        server: String,
+        /// Name of the tool
+        tool: String,
+        /// Arguments as a JSON object
+        #[arg(long, default_value = "{}")]
+        args: String,
+    },
+    /// List resources, of one server or of all of them
+    Resources {
+        /// Server ID from the config file
+        server: Option<String>,
+    },
+    /// List prompts, of one server or of all of them
+    Prompts {
+        /// Server ID from the config file
+        server: Option<String>,
+    },
+    /// Chat with a model that can use the servers' tools
+    Chat(ChatArgs),
+    /// Serve an agent with the servers' tools as an MCP server on stdio
+    Serve(ServeArgs),
+    /// Serve the tools, resources and prompts of all servers as one MCP server
+    Gateway {
+        /// Server to include; repeat for several, all servers when omitted
+        #[arg(long = "server", short = 's')]
+        servers: Vec<String>,
+
+        /// Serve Streamable HTTP on this address instead of stdio, such as `127.0.0.1:8080`
+        #[arg(long)]
+        http: Option<SocketAddr>,
+
+        /// Endpoint path when serving HTTP
+        #[arg(long, default_value = "/mcp", requires = "http")]
+        path: String,
+
+        /// Browser origin allowed besides loopback ones; repeat for several
+        #[arg(long = "allow-origin", requires = "http")]
+        allowed_origins: Vec<String>,
+
+        /// Bearer token HTTP clients must send
+        #[arg(long, env = "MCP_RIG_GATEWAY_TOKEN", requires = "http")]
+        token: Option<String>,
+    },
+}
+
+/// How to build the agent for `chat` and `serve`.
+#[derive(Args)]
+struct AgentArgs {
+    /// Model provider; the API key is read from the provider's usual environment variable
+    #[arg(long, value_enum, default_value_t = Provider::Openai)]
+    provider: Provider,
+
+    /// Model name, such as `gpt-4o` or `claude-3-5-sonnet-latest`
+    #[arg(long, short)]
+    model: String,
+
+    /// File with the system prompt
+    #[arg(long)]
+    preamble: Option<PathBuf>,
+
+    /// Server whose tools the model may use; repeat for several, all servers when omitted
+    #[arg(long = "server", short = 's')]
+    servers: Vec<String>,
+
+    /// Tool name pattern that needs approval before running, such as `write_*`; repeatable.
+    /// `serve` refuses these calls since stdin carries the protocol
+    #[arg(long = "approve")]
+    approve: Vec<String>,
+
+    /// Model requests allowed per reply, including rounds of tool calls
+    #[arg(long, default_value_t = 10)]
+    max_steps: usize,
+
+    /// Sampling temperature
+    #[arg(long)]
+    temperature: Option<f64>,
+}
+
+#[derive(Args)]
+struct ChatArgs {
+    #[command(flatten)]
+    agent: AgentArgs,
+
+    /// Transcript file to continue from and save to after every reply
+    #[arg(long)]
+    transcript: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    #[command(flatten)]
+    agent: AgentArgs,
+
+    /// Name of the tool that prompts the agent
+    #[arg(long, default_value = "ask_agent")]
+    name: String,
+
+    /// Description of the tool, telling calling models what the agent is for
+    #[arg(
+        long,
+        default_value = "Ask an assistant that can use its own tools to answer or carry out a request"
+    )]
+    description: String,
+}
+
+/// What to do with the agent once it is built.
+#[derive(Clone, Copy)]
+enum Mode<'a> {
+    Chat(&'a ChatArgs),
+    Serve(&'a ServeArgs),
+}
+
+impl Mode<'_> {
+    fn agent_args(&self) -> &AgentArgs {
+        match self {
+            Mode::Chat(args) => &args.agent,
+            Mode::Serve(args) => &args.agent,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Provider {
+    Openai,
+    Anthropic,
+    Gemini,
+    Ollama,
+    Deepseek,
+    Groq,
+    Openrouter,
+}
+
+type CliResult = Result<(), Box<dyn std::error::Error>>;
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
+async fn main() {
+    // Logs go to stderr so they never mix with the output
     tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
         .with_env_filter(
-            EnvFilter::from_default_env()
-                .add_directive("mcp_client=debug".parse().unwrap())
-                .add_directive("rig_core=debug".parse().unwrap())
-                .add_directive("mcp_rig_integration=debug".parse().unwrap()),
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
         )
         .init();
 
-    // Create and configure the MCP connection manager
-    let mut connection_manager = McpConnectionManager::with_timeout(Duration::from_secs(30));
-
-    // Add a Git client using StdioTransport
-    connection_manager
-        .add_stdio_client(
-            "git-client".to_string(),
-            "uvx",
-            vec!["mcp-server-git".to_string()],
-            HashMap::new(),
-            ClientInfo {
-                name: "rig-integration-git".to_string(),
-                version: "1.0.0".to_string(),
-            },
-        )
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> CliResult {
+    let config = McpConfig::load(&cli.config)?;
+    let mut manager = McpConnectionManager::with_timeout(Duration::from_secs(cli.timeout));
+
+    match &cli.command {
+        Command::Servers => print_servers(&cli, &config),
+        Command::Tools { server } => {
+            let client = connect(&mut manager, &config, server).await?;
+            let tools = list_tools(client.as_ref()).await?;
+            print_tools(&cli, &tools)
+        }
+        Command::Call { server, tool, args } => {
+            let args: Value = serde_json::from_str(args)
+                .map_err(|e| format!("--args is not valid JSON: {}", e))?;
+            connect(&mut manager, &config, server).await?;
+            let adapter = tool_adapter(&config, &manager, server, tool).await?;
+            let output = adapter.call(McpToolArgs { args }).await?;
+            print_output(&cli, &output)
+        }
+        Command::Resources { server } => {
+            let mut resources = Vec::new();
+            for id in servers(&config, server.as_deref())? {
+                let client = connect(&mut manager, &config, &id).await?;
+                for resource in list_resources(client.as_ref()).await? {
+                    resources.push((id.clone(), resource));
+                }
+            }
+            print_resources(&cli, &resources)
+        }
+        Command::Prompts { server } => {
+            let mut prompts = Vec::new();
+            for id in servers(&config, server.as_deref())? {
+                let client = connect(&mut manager, &config, &id).await?;
+                for prompt in list_prompts(client.as_ref()).await? {
+                    prompts.push((id.clone(), prompt));
+                }
+            }
+            print_prompts(&cli, &prompts)
+        }
+        Command::Chat(args) => start_agent(&config, &mut manager, Mode::Chat(args)).await,
+        Command::Serve(args) => start_agent(&config, &mut manager, Mode::Serve(args)).await,
+        Command::Gateway {
+            servers,
+            http,
+            path,
+            allowed_origins,
+            token,
+        } => {
+            let mut options = HttpServerOptions {
+                allowed_origins: allowed_origins.clone(),
+                ..HttpServerOptions::default()
+            };
+            if let Some(token) = token {
+                options = options.with_bearer_token(token);
+            }
+            run_gateway(config, &mut manager, servers, *http, path, options).await
+        }
+    }
+}
+
+/// Connect to the servers and serve them as one.
+async fn run_gateway(
+    config: McpConfig,
+    manager: &mut McpConnectionManager,
+    ids: &[String],
+    http: Option<SocketAddr>,
+    path: &str,
+    options: HttpServerOptions,
+) -> CliResult {
+    let ids = select_servers(&config, ids)?;
+    // Subscribe first so nothing sent while connecting is lost
+    let notifications = manager.subscribe_notifications();
+    for id in &ids {
+        connect(manager, &config, id).await?;
+    }
+    let gateway = McpGateway::from_manager(manager, config).with_notifications(notifications);
+
+    match http {
+        Some(addr) => {
+            let listener = tokio::net::TcpListener::bind(addr)
+                .await
+                .map_err(|e| format!("failed to listen on {}: {}", addr, e))?;
+            tracing::info!(%addr, path, servers = ?ids, "serving gateway over HTTP");
+            if options.bearer_token.is_none() {
+                tracing::warn!("no bearer token set; any local process can use the gateway");
+            }
+            Ok(serve_http_with(Arc::new(gateway), listener, path, options).await?)
+        }
+        None => {
+            tracing::info!(servers = ?ids, "serving gateway on stdio");
+            Ok(serve_stdio(Arc::new(gateway)).await?)
+        }
+    }
+}
+
+/// Connect to the servers an agent uses and start it with the chosen provider.
+async fn start_agent(
+    config: &McpConfig,
+    manager: &mut McpConnectionManager,
+    mode: Mode<'_>,
+) -> CliResult {
+    let args = mode.agent_args();
+    let ids = select_servers(config, &args.servers)?;
+    for id in &ids {
+        connect(manager, config, id).await?;
+    }
+
+    let model = args.model.as_str();
+    match args.provider {
+        Provider::Openai => {
+            let client = providers::openai::Client::from_env();
+            run_agent(
+                client.agent(model),
+                client.completion_model(model),
+                config,
+                manager,
+                &ids,
+                mode,
+            )
+            .await
+        }
+        Provider::Anthropic => {
+            let client = providers::anthropic::Client::from_env();
+            run_agent(
+                client.agent(model),
+                client.completion_model(model),
+                config,
+                manager,
+                &ids,
+                mode,
+            )
+            .await
+        }
+        Provider::Gemini => {
+            let client = providers::gemini::Client::from_env();
+            run_agent(
+                client.agent(model),
+                client.completion_model(model),
+                config,
+                manager,
+                &ids,
+                mode,
+            )
+            .await
+        }
+        Provider::Ollama => {
+            let client = providers::ollama::Client::new();
+            run_agent(
+                client.agent(model),
+                client.completion_model(model),
+                config,
+                manager,
+                &ids,
+                mode,
+            )
+            .await
+        }
+        Provider::Deepseek => {
+            let client = providers::deepseek::Client::from_env();
+            run_agent(
+                client.agent(model),
+                client.completion_model(model),
+                config,
+                manager,
+                &ids,
+                mode,
+            )
+            .await
+        }
+        Provider::Groq => {
+            let client = providers::groq::Client::from_env();
+            run_agent(
+                client.agent(model),
+                client.completion_model(model),
+                config,
+                manager,
+                &ids,
+                mode,
+            )
+            .await
+        }
+        Provider::Openrouter => {
+            let client = providers::openrouter::Client::from_env();
+            run_agent(
+                client.agent(model),
+                client.completion_model(model),
+                config,
+                manager,
+                &ids,
+                mode,
+            )
+            .await
+        }
+    }
+}
+
+fn client_info() -> ClientInfo {
+    ClientInfo {
+        name: "mcp-rig".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    }
+}
+
+/// Get the IDs of the requested server, or of every server.
+fn servers(config: &McpConfig, server: Option<&str>) -> Result<Vec<String>, String> {
+    match server {
+        Some(id) if config.server(id).is_some() => Ok(vec![id.to_string()]),
+        Some(id) => Err(unknown_server(id)),
+        None => Ok(config.servers.keys().cloned().collect()),
+    }
+}
+
+/// Get the IDs of the requested servers, or of every server when none are.
+fn select_servers(config: &McpConfig, ids: &[String]) -> Result<Vec<String>, String> {
+    match ids {
+        [] => servers(config, None),
+        ids => ids
+            .iter()
+            .map(|id| servers(config, Some(id)).map(|mut ids| ids.remove(0)))
+            .collect(),
+    }
+}
+
+fn unknown_server(id: &str) -> String {
+    format!("no server named '{}' in the config file", id)
+}
+
+/// Connect to one server from the config file.
+async fn connect(
+    manager: &mut McpConnectionManager,
+    config: &McpConfig,
+    id: &str,
+) -> Result<Arc<dyn McpClientTrait>, Box<dyn std::error::Error>> {
+    let server = config.server(id).ok_or_else(|| unknown_server(id))?;
+    manager
+        .add_server(id.to_string(), server, client_info())
         .await?;
+    Ok(manager.get_client(id).ok_or_else(|| unknown_server(id))?)
+}
 
-    // Add an Echo client using SseTransport
-    connection_manager
-        .add_sse_client(
-            "echo-client".to_string(),
-            "http://localhost:8000/sse",
-            HashMap::new(),
-            ClientInfo {
-                name: "rig-integration-echo".to_string(),
-                version: "1.0.0".to_string(),
-            },
-        )
+/// Build the adapter for a tool with the config's settings applied.
+///
+/// As in `chat`, tools marked `requireApproval` in the config prompt on the
+/// terminal and abandoned calls are cancelled on the server.
+async fn tool_adapter(
+    config: &McpConfig,
+    manager: &McpConnectionManager,
+    server: &str,
+    name: &str,
+) -> Result<McpToolAdapter, Box<dyn std::error::Error>> {
+    let client = manager
+        .get_client(server)
+        .ok_or_else(|| unknown_server(server))?;
+    let tool = list_tools(client.as_ref())
+        .await?
+        .into_iter()
+        .find(|tool| tool.name == name)
+        .ok_or_else(|| format!("server '{}' has no tool named '{}'", server, name))?;
+    let mut adapter = McpToolAdapter::new(client, tool.name, tool.description, tool.input_schema)
+        .with_approval(ApprovalGate::new(Arc::new(StdinApprover::new())));
+    if let Some(canceller) = manager.get_canceller(server) {
+        adapter = adapter.with_canceller(canceller);
+    }
+    Ok(config.configure_adapter(server, adapter))
+}
+
+async fn list_tools(
+    client: &dyn McpClientTrait,
+) -> Result<Vec<mcp_spec::Tool>, Box<dyn std::error::Error>> {
+    let mut tools = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = client.list_tools(cursor).await?;
+        tools.extend(page.tools);
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(tools),
+        }
+    }
+}
+
+async fn list_resources(
+    client: &dyn McpClientTrait,
+) -> Result<Vec<mcp_spec::resource::Resource>, Box<dyn std::error::Error>> {
+    let mut resources = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = client.list_resources(cursor).await?;
+        resources.extend(page.resources);
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(resources),
+        }
+    }
+}
+
+async fn list_prompts(
+    client: &dyn McpClientTrait,
+) -> Result<Vec<mcp_spec::prompt::Prompt>, Box<dyn std::error::Error>> {
+    // The client lists no resources for servers without them, but fails for prompts
+    match list_all_prompts(client).await {
+        Ok(prompts) => Ok(prompts),
+        Err(mcp_client::Error::RpcError { code, .. }) if code == METHOD_NOT_FOUND => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Build the agent with every server's tools, then chat or serve.
+async fn run_agent<M: CompletionModel + 'static>(
+    mut builder: AgentBuilder<M>,
+    model: M,
+    config: &McpConfig,
+    manager: &McpConnectionManager,
+    ids: &[String],
+    mode: Mode<'_>,
+) -> CliResult {
+    let args = mode.agent_args();
+    if let Some(path) = &args.preamble {
+        let preamble = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read preamble {}: {}", path.display(), e))?;
+        builder = builder.preamble(&preamble);
+    }
+    if let Some(temperature) = args.temperature {
+        builder = builder.temperature(temperature);
+    }
+
+    // The chat loop and approval prompts read stdin through one buffer
+    let stdin = Arc::new(Mutex::new(BufReader::new(tokio::io::stdin())));
+    // Tools marked `requireApproval` in the config are gated too
+    let approver: Arc<dyn ApprovalHandler> = match mode {
+        Mode::Chat(_) => Arc::new(StdinApprover::with_reader(Arc::clone(&stdin))),
+        Mode::Serve(_) => Arc::new(AutoDenyApprover::new(
+            "this tool needs approval, which is not available when serving",
+        )),
+    };
+    let gate = ApprovalGate::new(approver).for_tools(args.approve.clone());
+    // Servers share one name map so tools of the same name stay distinct
+    let mut names = ToolNameMap::default();
+    for id in ids {
+        let client = manager.get_client(id).ok_or_else(|| unknown_server(id))?;
+        let canceller = manager.get_canceller(id);
+        register_mcp_tools_with(client, &mut builder, model.clone(), &mut names, |adapter| {
+            let mut adapter = adapter.with_approval(gate.clone());
+            if let Some(canceller) = &canceller {
+                adapter = adapter.with_canceller(Arc::clone(canceller));
+            }
+            config.configure_adapter(id, adapter)
+        })
         .await?;
+    }
+    let agent = builder.build();
+
+    match mode {
+        Mode::Chat(chat_args) => run_chat(agent, ids, chat_args, &stdin).await,
+        Mode::Serve(serve_args) => {
+            let server = RigServer::new("mcp-rig", env!("CARGO_PKG_VERSION"))
+                .with_max_steps(args.max_steps)
+                .with_agent(&serve_args.name, &serve_args.description, agent);
+            tracing::info!(tool = %serve_args.name, servers = ?ids, "serving agent on stdio");
+            Ok(serve_stdio(Arc::new(server)).await?)
+        }
+    }
+}
+
+/// Run the interactive chat loop.
+async fn run_chat<M: CompletionModel>(
+    agent: Agent<M>,
+    ids: &[String],
+    args: &ChatArgs,
+    stdin: &Mutex<BufReader<Stdin>>,
+) -> CliResult {
+    let transcript = match &args.transcript {
+        Some(path) => Transcript::load(path)?,
+        None => Transcript::default(),
+    };
+    let restored = transcript.messages.len();
+    let mut session = ChatSession::new(agent)
+        .with_transcript(transcript)
+        .with_max_steps(args.agent.max_steps);
+
+    println!(
+        "Chatting with {} using tools from: {}",
+        args.agent.model,
+        ids.join(", ")
+    );
+    if restored > 0 {
+        println!("Continuing a conversation of {} messages.", restored);
+    }
+    println!("Commands: /save <file>, /clear, /exit\n");
+
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        std::io::stdout().flush()?;
+        line.clear();
+        if stdin.lock().await.read_line(&mut line).await? == 0 {
+            break;
+        }
+        let line = line.trim();
+        match line.split_once(' ').unwrap_or((line, "")) {
+            ("", _) => continue,
+            ("/exit" | "/quit" | "exit" | "quit", _) => break,
+            ("/clear", _) => {
+                session.clear();
+                println!("Conversation cleared.");
+                continue;
+            }
+            ("/save", path) if !path.trim().is_empty() => {
+                save_transcript(&session, Path::new(path.trim()));
+                continue;
+            }
+            ("/save", _) => {
+                println!("Usage: /save <file>");
+                continue;
+            }
+            _ => {}
+        }
+
+        match session.send(line, show_event).await {
+            Ok(answer) => println!("\n{}\n", answer),
+            Err(e) => eprintln!("error: {}", e),
+        }
+        if let Some(path) = &args.transcript {
+            save_transcript(&session, path);
+        }
+    }
+    Ok(())
+}
 
-    // // Initialize the Rig client
-    // let rig_client = RigClient::new()?;
-
-    // // Create a Rig agent with MCP tools from the git client
-    // let git_client = connection_manager
-    //     .get_client("git-client")
-    //     .ok_or("Git client not found")?;
-
-    // let agent = setup_rig_with_mcp_rag(
-    //     git_client,
-    //     &rig_client,
-    //     "gpt-4-turbo",             // Model
-    //     "text-embedding-ada-002",  // Embedding model
-    //     "You are a helpful assistant with access to Git tools. You can help users manage their repositories.",
-    //     5,                         // Max dynamic tools
-    // ).await?;
-
-    // // Run a conversation with the agent
-    // println!("Starting conversation with the agent...");
-
-    // let response = agent
-    //     .chat("Can you check the git status of the current repository?")
-    //     .await?;
-    // println!("Agent response: {}", response);
-
-    // let response = agent
-    //     .chat("What branches are available and which one am I on?")
-    //     .await?;
-    // println!("Agent response: {}", response);
-
-    // // Now try with the echo client
-    // let echo_client = connection_manager
-    //     .get_client("echo-client")
-    //     .ok_or("Echo client not found")?;
-
-    // let echo_agent = setup_rig_with_mcp_rag(
-    //     echo_client,
-    //     &rig_client,
-    //     "gpt-4-turbo",             // Model
-    //     "text-embedding-ada-002",  // Embedding model
-    //     "You are a helpful assistant with access to Echo tools. You can help users test message passing.",
-    //     3,                         // Max dynamic tools
-    // ).await?;
-
-    // // Run a conversation with the echo agent
-    // let response = echo_agent
-    //     .chat("Can you echo a message back to me?")
-    //     .await?;
-    // println!("Echo agent response: {}", response);
+fn save_transcript<M: CompletionModel>(session: &ChatSession<M>, path: &Path) {
+    match session.transcript().save(path) {
+        Ok(()) => tracing::debug!(path = %path.display(), "saved transcript"),
+        Err(e) => eprintln!("error: {}", e),
+    }
+}
+
+/// Show tool activity inline while the model works.
+fn show_event(event: ChatEvent<'_>) {
+    match event {
+        ChatEvent::Text(text) => println!("{}", text),
+        ChatEvent::ToolCall { name, arguments } => println!("  [call] {} {}", name, arguments),
+        ChatEvent::ToolResult { name, output } => {
+            let rendered = match serde_json::from_str::<Value>(output) {
+                Ok(value) => render_output(&value),
+                Err(_) => output.to_string(),
+            };
+            let mut preview: String = rendered.chars().take(CHAT_RESULT_PREVIEW_CHARS).collect();
+            if preview.len() < rendered.len() {
+                preview.push_str(" ...");
+            }
+            println!("  [result] {}: {}", name, preview.replace('\n', "\n    "));
+        }
+        ChatEvent::ToolError { name, error } => println!("  [error] {}: {}", name, error),
+    }
+}
 
+fn print_json(value: &Value) -> CliResult {
+    println!("{}", serde_json::to_string_pretty(value)?);
     Ok(())
 }
+
+/// Describe how a server is reached, for the server list
+fn target(server: &ServerConfig) -> String {
+    match (&server.command, &server.url) {
+        (Some(command), _) => std::iter::once(command.as_str())
+            .chain(server.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" "),
+        (None, Some(url)) => url.clone(),
+        (None, None) => String::new(),
+    }
+}
+
+fn print_servers(cli: &Cli, config: &McpConfig) -> CliResult {
+    if cli.json {
+        let servers: Vec<Value> = config
+            .servers
+            .iter()
+            .map(|(id, server)| {
+                json!({
+                    "id": id,
+                    "transport": server.transport().ok(),
+                    "config": server,
+                })
+            })
+            .collect();
+        return print_json(&Value::Array(servers));
+    }
+
+    let width = config.servers.keys().map(String::len).max().unwrap_or(0);
+    for (id, server) in &config.servers {
+        let transport = match server.transport() {
+            Ok(kind) => serde_json::to_value(kind)?
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            Err(_) => "invalid".to_string(),
+        };
+        println!("{:width$}  {:9}  {}", id, transport, target(server));
+    }
+    Ok(())
+}
+
+fn print_tools(cli: &Cli, tools: &[mcp_spec::Tool]) -> CliResult {
+    if cli.json {
+        return print_json(&serde_json::to_value(tools)?);
+    }
+    for (i, tool) in tools.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}", tool.name);
+        for line in tool.description.lines() {
+            println!("  {}", line);
+        }
+        let schema = serde_json::to_string_pretty(&tool.input_schema)?;
+        println!("  schema:");
+        for line in schema.lines() {
+            println!("    {}", line);
+        }
+    }
+    Ok(())
+}
+
+/// Print a tool result.
+fn print_output(cli: &Cli, output: &Value) -> CliResult {
+    if cli.json {
+        return print_json(output);
+    }
+    println!("{}", render_output(output));
+    Ok(())
+}
+
+/// Render a tool result: text as-is, anything else as JSON.
+fn render_output(output: &Value) -> String {
+    let pretty = |value: &Value| serde_json::to_string_pretty(value).unwrap_or_default();
+    match output {
+        Value::String(text) => text.clone(),
+        Value::Array(content) => content
+            .iter()
+            .map(|item| match item.get("text").and_then(Value::as_str) {
+                Some(text) if item["type"] == "text" => text.to_string(),
+                _ => pretty(item),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        other => pretty(other),
+    }
+}
+
+fn print_resources(cli: &Cli, resources: &[(String, mcp_spec::resource::Resource)]) -> CliResult {
+    if cli.json {
+        let resources = resources
+            .iter()
+            .map(|(server, resource)| with_server(server, resource))
+            .collect::<Result<Vec<_>, _>>()?;
+        return print_json(&Value::Array(resources));
+    }
+    for (server, resource) in resources {
+        print!(
+            "{}  {}  {} ({})",
+            server, resource.uri, resource.name, resource.mime_type
+        );
+        match &resource.description {
+            Some(description) => println!(" - {}", description),
+            None => println!(),
+        }
+    }
+    Ok(())
+}
+
+fn print_prompts(cli: &Cli, prompts: &[(String, mcp_spec::prompt::Prompt)]) -> CliResult {
+    if cli.json {
+        let prompts = prompts
+            .iter()
+            .map(|(server, prompt)| with_server(server, prompt))
+            .collect::<Result<Vec<_>, _>>()?;
+        return print_json(&Value::Array(prompts));
+    }
+    for (server, prompt) in prompts {
+        print!("{}  {}", server, prompt.name);
+        match &prompt.description {
+            Some(description) => println!(" - {}", description),
+            None => println!(),
+        }
+        for argument in prompt.arguments.iter().flatten() {
+            let required = if argument.required == Some(true) {
+                " (required)"
+            } else {
+                ""
+            };
+            print!("    {}{}", argument.name, required);
+            match &argument.description {
+                Some(description) => println!(": {}", description),
+                None => println!(),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Serialize an item with the ID of the server it came from.
+fn with_server(server: &str, item: &impl serde::Serialize) -> serde_json::Result<Value> {
+    let mut value = serde_json::to_value(item)?;
+    if let Value::Object(map) = &mut value {
+        map.insert("server".to_string(), json!(server));
+    }
+    Ok(value)
+}
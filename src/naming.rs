@@ -0,0 +1,214 @@
+// src/naming.rs
+
+//! Tool name sanitization for provider naming rules.
+//!
+//! MCP places no restrictions on tool names, but most LLM providers only
+//! accept names matching `^[a-zA-Z0-9_-]{1,64}$`. This module maps MCP tool
+//! names onto that alphabet deterministically, truncating long names and
+//! appending a short hash so distinct names stay distinct. `ToolNameMap`
+//! keeps the mapping reversible and detects collisions between tools.
+
+use crate::error::McpRigIntegrationError;
+use std::collections::HashMap;
+
+/// Maximum tool name length accepted by OpenAI and most other providers.
+pub const DEFAULT_MAX_TOOL_NAME_LEN: usize = 64;
+
+/// Length of the `_xxxxxxxx` hash suffix appended to altered names.
+const HASH_SUFFIX_LEN: usize = 9;
+
+/// Number of times `insert_distinct` accepts the same MCP name.
+const MAX_DUPLICATES: usize = 64;
+
+/// 32-bit FNV-1a hash, used because it is stable across Rust releases.
+fn fnv1a(input: &str) -> u32 {
+    input.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+fn with_hash_suffix(name: &str, original: &str, max_len: usize) -> String {
+    let keep = max_len.saturating_sub(HASH_SUFFIX_LEN).max(1);
+    let prefix: String = name.chars().take(keep).collect();
+    format!("{}_{:08x}", prefix, fnv1a(original))
+}
+
+/// Sanitize an MCP tool name so that providers accept it.
+///
+/// Characters outside `[a-zA-Z0-9_-]` are replaced with `_`. Names longer
+/// than `max_len` are truncated and suffixed with a hash of the original name.
+/// Names that are already valid are returned unchanged.
+///
+/// # Example
+///
+/// ```rust
+/// use mcp_rig::sanitize_tool_name;
+///
+/// assert_eq!(sanitize_tool_name("git.status", 64), "git_status");
+/// assert_eq!(sanitize_tool_name("read_file", 64), "read_file");
+/// assert_eq!(sanitize_tool_name(&"x".repeat(100), 64).len(), 64);
+/// ```
+pub fn sanitize_tool_name(name: &str, max_len: usize) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if sanitized.is_empty() {
+        sanitized = "tool".to_string();
+    }
+
+    if sanitized.len() > max_len {
+        sanitized = with_hash_suffix(&sanitized, name, max_len);
+    }
+    sanitized
+}
+
+/// Reversible mapping between MCP tool names and provider-safe names.
+///
+/// When two MCP names sanitize to the same provider name, the later one is
+/// disambiguated with a hash suffix. Because the suffix is derived from the
+/// original name, the mapping is the same every time the same tools are
+/// registered in the same order.
+#[derive(Debug, Clone)]
+pub struct ToolNameMap {
+    /// Maximum length of sanitized names
+    max_len: usize,
+    /// Original MCP name to sanitized name
+    forward: HashMap<String, String>,
+    /// Sanitized name to original MCP name
+    reverse: HashMap<String, String>,
+}
+
+impl Default for ToolNameMap {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_TOOL_NAME_LEN)
+    }
+}
+
+impl ToolNameMap {
+    /// Create an empty map producing names of at most `max_len` characters.
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            max_len: max_len.max(HASH_SUFFIX_LEN + 1),
+            forward: HashMap::new(),
+            reverse: HashMap::new(),
+        }
+    }
+
+    /// Add an MCP tool name and return its provider-safe name.
+    ///
+    /// Adding the same name twice returns the same result. Fails with
+    /// `McpRigIntegrationError::NameCollision` if no unique name can be found.
+    pub fn insert(&mut self, original: &str) -> Result<String, McpRigIntegrationError> {
+        if let Some(existing) = self.forward.get(original) {
+            return Ok(existing.clone());
+        }
+
+        let mut candidate = sanitize_tool_name(original, self.max_len);
+        if let Some(other) = self.reverse.get(&candidate) {
+            tracing::warn!(
+                tool = original,
+                colliding_with = %other,
+                name = %candidate,
+                "sanitized tool name collides, adding hash suffix"
+            );
+            candidate = with_hash_suffix(&candidate, original, self.max_len);
+        }
+        if let Some(other) = self.reverse.get(&candidate) {
+            return Err(McpRigIntegrationError::NameCollision(format!(
+                "tools '{}' and '{}' both map to '{}'",
+                other, original, candidate
+            )));
+        }
+
+        self.forward.insert(original.to_string(), candidate.clone());
+        self.reverse.insert(candidate.clone(), original.to_string());
+        Ok(candidate)
+    }
+
+    /// Add an MCP tool name that may already be in the map and return a new
+    /// provider-safe name for it.
+    ///
+    /// This is for tools of the same name offered by different servers: the
+    /// first one is added as with `insert`, later ones get a hash suffix.
+    /// `sanitized` keeps returning the first name, while `original` maps
+    /// every allocated name back to the MCP name.
+    pub fn insert_distinct(&mut self, original: &str) -> Result<String, McpRigIntegrationError> {
+        if !self.forward.contains_key(original) {
+            return self.insert(original);
+        }
+
+        let base = sanitize_tool_name(original, self.max_len);
+        for copy in 2..=MAX_DUPLICATES {
+            let candidate =
+                with_hash_suffix(&base, &format!("{}#{}", original, copy), self.max_len);
+            if !self.reverse.contains_key(&candidate) {
+                tracing::warn!(
+                    tool = original,
+                    name = %candidate,
+                    "tool name is already registered, adding hash suffix"
+                );
+                self.reverse.insert(candidate.clone(), original.to_string());
+                return Ok(candidate);
+            }
+        }
+        Err(McpRigIntegrationError::NameCollision(format!(
+            "tool '{}' is registered more than {} times",
+            original, MAX_DUPLICATES
+        )))
+    }
+
+    /// Add a provider name chosen by the caller for an MCP tool.
+    ///
+    /// The name is used as given. Fails with
+    /// `McpRigIntegrationError::NameCollision` if another tool already has it.
+    pub fn reserve(&mut self, original: &str, name: &str) -> Result<(), McpRigIntegrationError> {
+        if let Some(other) = self.reverse.get(name) {
+            return Err(McpRigIntegrationError::NameCollision(format!(
+                "tools '{}' and '{}' both map to '{}'",
+                other, original, name
+            )));
+        }
+        self.forward
+            .entry(original.to_string())
+            .or_insert_with(|| name.to_string());
+        self.reverse.insert(name.to_string(), original.to_string());
+        Ok(())
+    }
+
+    /// Release a provider name so that it can be allocated again.
+    pub fn remove(&mut self, name: &str) {
+        if let Some(original) = self.reverse.remove(name) {
+            if self.forward.get(&original).map(String::as_str) == Some(name) {
+                self.forward.remove(&original);
+            }
+        }
+    }
+
+    /// Get the provider-safe name for an MCP tool name
+    pub fn sanitized(&self, original: &str) -> Option<&str> {
+        self.forward.get(original).map(String::as_str)
+    }
+
+    /// Get the original MCP name for a provider-safe name
+    pub fn original(&self, sanitized: &str) -> Option<&str> {
+        self.reverse.get(sanitized).map(String::as_str)
+    }
+
+    /// Get the number of provider-safe names in the map
+    pub fn len(&self) -> usize {
+        self.reverse.len()
+    }
+
+    /// Check if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.reverse.is_empty()
+    }
+}
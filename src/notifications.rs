@@ -0,0 +1,72 @@
+// src/notifications.rs
+
+//! Notifications sent by MCP servers.
+//!
+//! Servers send notifications such as `notifications/tools/list_changed`,
+//! progress updates and log messages alongside their responses. The client
+//! has no use for them, so transports drop them unless they were given a
+//! `NotificationSink`, which tags each notification with the client ID and
+//! passes it on. `McpConnectionManager::subscribe_notifications` sets this
+//! up for every client the manager adds.
+
+use mcp_spec::protocol::JsonRpcNotification;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+/// A notification from one of the servers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerNotification {
+    /// The ID of the client whose server sent it
+    pub client_id: String,
+    /// The notification method, such as `notifications/progress`
+    pub method: String,
+    /// The notification parameters, `Null` if there were none
+    pub params: Value,
+}
+
+/// Where a transport delivers the notifications its server sends.
+#[derive(Debug, Clone)]
+pub struct NotificationSink {
+    /// The client ID attached to every notification
+    client_id: String,
+    /// The receiving end
+    sender: mpsc::UnboundedSender<ServerNotification>,
+}
+
+impl NotificationSink {
+    /// Deliver notifications to `sender`, tagged with `client_id`.
+    pub fn new(
+        client_id: impl Into<String>,
+        sender: mpsc::UnboundedSender<ServerNotification>,
+    ) -> Self {
+        Self {
+            client_id: client_id.into(),
+            sender,
+        }
+    }
+
+    /// Get the client ID attached to notifications
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// Pass on a notification; it is dropped if nobody is listening.
+    pub fn deliver(&self, notification: JsonRpcNotification) {
+        let notification = ServerNotification {
+            client_id: self.client_id.clone(),
+            method: notification.method,
+            params: notification.params.unwrap_or(Value::Null),
+        };
+        if self.sender.send(notification).is_err() {
+            tracing::debug!(client_id = %self.client_id, "dropping notification, nobody is listening");
+        }
+    }
+}
+
+/// Deliver a notification to `sink`, or log and drop it.
+pub(crate) fn deliver(sink: Option<&NotificationSink>, notification: JsonRpcNotification) {
+    match sink {
+        Some(sink) => sink.deliver(notification),
+        None => tracing::debug!(method = %notification.method, "ignoring server notification"),
+    }
+}
@@ -0,0 +1,201 @@
+// src/overrides.rs
+
+//! Tool description and schema overrides.
+//!
+//! Third-party MCP servers often ship terse or misleading descriptions and
+//! overly permissive schemas. A `ToolOverride` patches what the model sees
+//! without touching the server: it can replace or extend the description,
+//! hide parameters behind fixed values, set defaults and tighten enums.
+//!
+//! Overrides are applied when an `McpToolAdapter` is built, either in code
+//! with `McpToolAdapter::with_override` or from the `tools` section of a
+//! server entry in the config file.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Changes applied to a tool's definition before it is shown to the model.
+///
+/// # Example
+///
+/// ```json
+/// {
+///   "appendDescription": "Only use this for files under the project root.",
+///   "hidden": { "encoding": "utf-8" },
+///   "defaults": { "maxResults": 20 },
+///   "enums": { "mode": ["read", "list"] }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ToolOverride {
+    /// Replacement for the server's description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Text appended to the (possibly replaced) description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub append_description: Option<String>,
+    /// Parameters removed from the schema and always sent with a fixed value.
+    ///
+    /// This uses the same mechanism as `McpToolAdapter::bind`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hidden: BTreeMap<String, Value>,
+    /// Default values advertised in the schema and used when the model omits them
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub defaults: BTreeMap<String, Value>,
+    /// Allowed values for parameters, replacing or narrowing any existing enum.
+    ///
+    /// Values the server's enum does not allow are dropped, and an override
+    /// that leaves no values is ignored.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub enums: BTreeMap<String, Vec<Value>>,
+}
+
+impl ToolOverride {
+    /// Replace the tool's description.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Append text to the tool's description.
+    pub fn append_description(mut self, text: impl Into<String>) -> Self {
+        self.append_description = Some(text.into());
+        self
+    }
+
+    /// Hide a parameter from the model and always send `value` for it.
+    pub fn hide(mut self, parameter: impl Into<String>, value: Value) -> Self {
+        self.hidden.insert(parameter.into(), value);
+        self
+    }
+
+    /// Set the default value for a parameter.
+    pub fn default_value(mut self, parameter: impl Into<String>, value: Value) -> Self {
+        self.defaults.insert(parameter.into(), value);
+        self
+    }
+
+    /// Restrict a parameter to the given values.
+    pub fn restrict(mut self, parameter: impl Into<String>, values: Vec<Value>) -> Self {
+        self.enums.insert(parameter.into(), values);
+        self
+    }
+
+    /// Produce the description shown to the model.
+    pub fn apply_description(&self, original: &str) -> String {
+        let mut description = self
+            .description
+            .clone()
+            .unwrap_or_else(|| original.to_string());
+        if let Some(extra) = &self.append_description {
+            if !description.is_empty() {
+                description.push_str("\n\n");
+            }
+            description.push_str(extra);
+        }
+        description
+    }
+
+    /// Apply defaults and enum restrictions to a parameter schema.
+    ///
+    /// Hidden parameters are left in place; use `hide_parameters` to remove
+    /// them from the schema that is advertised to the model.
+    pub fn apply_schema(&self, schema: &Value) -> Value {
+        let mut schema = schema.clone();
+        let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) else {
+            return schema;
+        };
+
+        for (name, default) in &self.defaults {
+            if let Some(Value::Object(property)) = properties.get_mut(name) {
+                property.insert("default".to_string(), default.clone());
+            }
+        }
+
+        for (name, allowed) in &self.enums {
+            let Some(Value::Object(property)) = properties.get_mut(name) else {
+                tracing::warn!(parameter = %name, "enum override for unknown parameter ignored");
+                continue;
+            };
+            let narrowed: Vec<Value> = match property.get("enum").and_then(Value::as_array) {
+                Some(existing) => {
+                    let kept: Vec<Value> = allowed
+                        .iter()
+                        .filter(|v| existing.contains(v))
+                        .cloned()
+                        .collect();
+                    if kept.len() != allowed.len() {
+                        tracing::warn!(
+                            parameter = %name,
+                            "enum override contains values the server does not allow; they were dropped"
+                        );
+                    }
+                    kept
+                }
+                None => allowed.clone(),
+            };
+            if narrowed.is_empty() {
+                // An empty enum can never be satisfied and would reject every call
+                tracing::warn!(
+                    parameter = %name,
+                    "enum override leaves no allowed values; the server's schema was kept"
+                );
+                continue;
+            }
+            property.insert("enum".to_string(), Value::Array(narrowed));
+        }
+
+        schema
+    }
+}
+
+/// Remove parameters from a schema's `properties` and `required` lists.
+pub fn hide_parameters<'a>(schema: &Value, names: impl IntoIterator<Item = &'a String>) -> Value {
+    let mut schema = schema.clone();
+    for name in names {
+        if let Some(Value::Object(properties)) = schema.get_mut("properties") {
+            properties.remove(name);
+        }
+        if let Some(Value::Array(required)) = schema.get_mut("required") {
+            required.retain(|r| r.as_str() != Some(name.as_str()));
+        }
+    }
+    schema
+}
+
+/// Merge fixed argument values into the model's arguments.
+///
+/// Fixed values always win. Returns the names of parameters whose
+/// model-provided value was overridden.
+pub fn merge_fixed_args(args: &mut Value, fixed: &Map<String, Value>) -> Vec<String> {
+    if fixed.is_empty() {
+        return Vec::new();
+    }
+    if !args.is_object() {
+        *args = Value::Object(Map::new());
+    }
+    let Value::Object(object) = args else {
+        return Vec::new();
+    };
+
+    let mut overridden = Vec::new();
+    for (name, value) in fixed {
+        if let Some(previous) = object.insert(name.clone(), value.clone()) {
+            if previous != *value {
+                overridden.push(name.clone());
+            }
+        }
+    }
+    overridden
+}
+
+/// Fill in default values for parameters the model omitted.
+pub fn fill_defaults(args: &mut Value, defaults: &Map<String, Value>) {
+    if let Value::Object(object) = args {
+        for (name, value) in defaults {
+            object.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+    }
+}
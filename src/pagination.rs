@@ -0,0 +1,61 @@
+// src/pagination.rs
+
+//! Paging through prompt listings.
+//!
+//! `ListPromptsResult` in mcp-spec 0.1.0 has no `next_cursor` field, so the
+//! cursor a server sends with a page of prompts is dropped by `McpClient`.
+//! As with request IDs for cancellation, the transport layer helps out: the
+//! `TrackedHandle` that `McpConnectionManager` wraps around every transport
+//! records the cursor of `prompts/list` responses sent from inside a
+//! `list_all_prompts` call.
+
+use mcp_client::client::McpClientTrait;
+use mcp_spec::prompt::Prompt;
+use mcp_spec::protocol::JsonRpcMessage;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+
+tokio::task_local! {
+    static PROMPT_CURSOR: PromptCursor;
+}
+
+/// The cursor of the last `prompts/list` page received in a listing.
+#[derive(Clone, Default)]
+struct PromptCursor(Arc<Mutex<Option<String>>>);
+
+/// Record the cursor of a `prompts/list` response, if a listing is running.
+pub(crate) fn record_prompt_cursor(response: &JsonRpcMessage) {
+    let JsonRpcMessage::Response(response) = response else {
+        return;
+    };
+    let cursor = response
+        .result
+        .as_ref()
+        .and_then(|result| result.get("nextCursor"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let _ = PROMPT_CURSOR.try_with(|slot| *slot.0.lock().unwrap() = cursor);
+}
+
+/// List every prompt offered by an MCP client, following pagination cursors.
+///
+/// Cursors are only seen for clients created by `McpConnectionManager`;
+/// other clients return their first page.
+pub async fn list_all_prompts(
+    client: &dyn McpClientTrait,
+) -> Result<Vec<Prompt>, mcp_client::Error> {
+    let mut prompts = Vec::new();
+    let mut cursor = None;
+    loop {
+        let slot = PromptCursor::default();
+        let page = PROMPT_CURSOR
+            .scope(slot.clone(), client.list_prompts(cursor))
+            .await?;
+        prompts.extend(page.prompts);
+        let next = slot.0.lock().unwrap().take();
+        match next {
+            Some(next) => cursor = Some(next),
+            None => return Ok(prompts),
+        }
+    }
+}
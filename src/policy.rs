@@ -0,0 +1,386 @@
+// src/policy.rs
+
+//! Declarative policy engine for tool arguments.
+//!
+//! Approval prompts rely on a human paying attention; policies are hard
+//! guarantees. A `PolicyEngine` evaluates a list of `PolicyRule`s over the
+//! tool name and selected argument values before `McpToolAdapter::call`
+//! dispatches a request. The first rule that fails denies the call, and the
+//! reason is returned to the model. Every decision is logged.
+//!
+//! Rules can be built in code or loaded from the `policies` section of the
+//! config file:
+//!
+//! ```json
+//! {
+//!   "policies": [
+//!     { "tools": ["read_file", "write_file"], "argument": "/path",
+//!       "condition": "pathUnder", "roots": ["/srv/project"] },
+//!     { "tools": ["git_push"], "condition": "deny", "reason": "pushing is not allowed" },
+//!     { "tools": ["fetch"], "argument": "/url",
+//!       "condition": "urlDomainIn", "domains": ["docs.rs", "github.com"] }
+//!   ]
+//! }
+//! ```
+
+use crate::config::take_key;
+use crate::error::McpRigIntegrationError;
+use crate::glob::glob_match;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::{Component, Path, PathBuf};
+
+/// A check applied to the argument values selected by a rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "condition", rename_all = "camelCase", deny_unknown_fields)]
+pub enum PolicyCondition {
+    /// The tool may not be called at all
+    Deny,
+    /// Paths must resolve to a location under one of the roots.
+    ///
+    /// Relative paths are resolved against the first root. The longest
+    /// existing part of the path and of each root is canonicalized, so
+    /// symlinks pointing outside a root are caught; the rest is normalized
+    /// lexically. Missing and non-string values are denied.
+    ///
+    /// This is a check made before the call, not a sandbox: the server
+    /// resolves the path again later, and the filesystem may have changed
+    /// by then.
+    #[serde(rename_all = "camelCase")]
+    PathUnder { roots: Vec<String> },
+    /// Strings must not contain any of the values, compared case-insensitively
+    #[serde(rename_all = "camelCase")]
+    NotContains { values: Vec<String> },
+    /// Values must equal one of the listed values
+    #[serde(rename_all = "camelCase")]
+    OneOf { values: Vec<Value> },
+    /// URLs must point at one of the domains or their subdomains.
+    ///
+    /// Missing and non-string values are denied.
+    #[serde(rename_all = "camelCase")]
+    UrlDomainIn { domains: Vec<String> },
+    /// Strings must match at least one of the glob patterns
+    #[serde(rename_all = "camelCase")]
+    Matches { patterns: Vec<String> },
+}
+
+/// A single policy rule.
+///
+/// Unknown keys are rejected when a rule is deserialized, so a misspelled
+/// setting can't silently weaken the rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", try_from = "Map<String, Value>")]
+pub struct PolicyRule {
+    /// Name used in logs; defaults to the rule's position
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Tool name patterns the rule applies to
+    #[serde(default = "all_tools")]
+    pub tools: Vec<String>,
+    /// JSON Pointer to the checked argument; `*` segments match every element.
+    ///
+    /// When omitted, the condition is checked against the whole argument object.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub argument: Option<String>,
+    /// The check to apply
+    #[serde(flatten)]
+    pub condition: PolicyCondition,
+    /// Explanation returned to the model when the rule denies a call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+fn all_tools() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+// serde can't combine `deny_unknown_fields` with the flattened condition, so
+// the rule's own keys are taken out first and the rest must be the condition's.
+impl TryFrom<Map<String, Value>> for PolicyRule {
+    type Error = serde_json::Error;
+
+    fn try_from(mut map: Map<String, Value>) -> Result<Self, Self::Error> {
+        let name = take_key(&mut map, "name")?;
+        let tools = take_key(&mut map, "tools")?.unwrap_or_else(all_tools);
+        let argument = take_key(&mut map, "argument")?;
+        let reason = take_key(&mut map, "reason")?;
+        let extra = map.keys().find(|key| *key != "condition").cloned();
+        let condition = serde_json::from_value(Value::Object(map))?;
+        // Unit variants accept any extra keys, so `deny` is checked here
+        if let (PolicyCondition::Deny, Some(key)) = (&condition, extra) {
+            return Err(serde::de::Error::custom(format!(
+                "unknown field `{}` for condition `deny`",
+                key
+            )));
+        }
+        Ok(Self {
+            name,
+            tools,
+            argument,
+            condition,
+            reason,
+        })
+    }
+}
+
+impl PolicyRule {
+    /// Create a rule applying `condition` to the tools matching `tools`.
+    pub fn new<I, S>(tools: I, condition: PolicyCondition) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            name: None,
+            tools: tools.into_iter().map(Into::into).collect(),
+            argument: None,
+            condition,
+            reason: None,
+        }
+    }
+
+    /// Apply the condition to the argument at the given JSON Pointer.
+    pub fn on_argument(mut self, pointer: impl Into<String>) -> Self {
+        self.argument = Some(pointer.into());
+        self
+    }
+
+    /// Set the name used in logs.
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set the explanation returned to the model on denial.
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    fn applies_to(&self, tool_name: &str) -> bool {
+        self.tools.iter().any(|p| glob_match(p, tool_name))
+    }
+
+    /// Check the rule, returning a description of the first violation.
+    fn check(&self, args: &Value) -> Option<String> {
+        if self.condition == PolicyCondition::Deny {
+            return Some("the tool is not allowed".to_string());
+        }
+
+        let values = match &self.argument {
+            Some(pointer) => {
+                let values = select(args, pointer);
+                // A missing path or URL can't be shown to be safe
+                let wildcard = pointer.split('/').any(|segment| segment == "*");
+                if values.is_empty() && !wildcard && self.condition.requires_value() {
+                    return Some(format!("argument '{}' is missing", pointer));
+                }
+                values
+            }
+            None => vec![args],
+        };
+        values
+            .into_iter()
+            .find_map(|value| check_condition(&self.condition, value))
+    }
+}
+
+/// Select the values addressed by a JSON Pointer with `*` wildcards.
+fn select<'a>(value: &'a Value, pointer: &str) -> Vec<&'a Value> {
+    let mut current = vec![value];
+    for segment in pointer.split('/').filter(|s| !s.is_empty()) {
+        let segment = segment.replace("~1", "/").replace("~0", "~");
+        current = current
+            .into_iter()
+            .flat_map(|value| -> Vec<&'a Value> {
+                match (value, segment.as_str()) {
+                    (Value::Array(items), "*") => items.iter().collect(),
+                    (Value::Object(members), "*") => members.values().collect(),
+                    (Value::Array(items), index) => index
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|i| items.get(i))
+                        .into_iter()
+                        .collect(),
+                    (Value::Object(members), key) => members.get(key).into_iter().collect(),
+                    _ => Vec::new(),
+                }
+            })
+            .collect();
+    }
+    current
+}
+
+fn check_condition(condition: &PolicyCondition, value: &Value) -> Option<String> {
+    match condition {
+        PolicyCondition::Deny => Some("the tool is not allowed".to_string()),
+        PolicyCondition::PathUnder { roots } => {
+            let Some(path) = value.as_str() else {
+                return Some(format!("{} is not a path", value));
+            };
+            let base = roots.first().map(PathBuf::from).unwrap_or_default();
+            let resolved = resolve_path(&base.join(path));
+            let allowed = roots
+                .iter()
+                .any(|root| resolved.starts_with(resolve_path(Path::new(root))));
+            (!allowed).then(|| format!("path '{}' is outside the allowed directories", path))
+        }
+        PolicyCondition::NotContains { values } => {
+            let text = value_text(value).to_lowercase();
+            values
+                .iter()
+                .find(|needle| text.contains(&needle.to_lowercase()))
+                .map(|needle| format!("'{}' is not allowed", needle))
+        }
+        PolicyCondition::OneOf { values } => {
+            (!values.contains(value)).then(|| format!("{} is not an allowed value", value))
+        }
+        PolicyCondition::UrlDomainIn { domains } => {
+            let Some(raw) = value.as_str() else {
+                return Some(format!("{} is not a URL", value));
+            };
+            let host = url::Url::parse(raw)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_ascii_lowercase));
+            let Some(host) = host else {
+                return Some(format!("'{}' is not a valid URL", raw));
+            };
+            let allowed = domains.iter().any(|domain| {
+                let domain = domain.to_ascii_lowercase();
+                host == domain || host.ends_with(&format!(".{}", domain))
+            });
+            (!allowed).then(|| format!("domain '{}' is not in the allowed list", host))
+        }
+        PolicyCondition::Matches { patterns } => {
+            let text = value_text(value);
+            (!patterns.iter().any(|p| glob_match(p, &text)))
+                .then(|| format!("'{}' does not match an allowed pattern", text))
+        }
+    }
+}
+
+impl PolicyCondition {
+    /// Whether the checked argument must be present for the call to pass.
+    fn requires_value(&self) -> bool {
+        matches!(self, Self::PathUnder { .. } | Self::UrlDomainIn { .. })
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Canonicalize the longest existing prefix of a path, then normalize the rest.
+fn resolve_path(path: &Path) -> PathBuf {
+    for ancestor in path.ancestors() {
+        if let Ok(real) = ancestor.canonicalize() {
+            let rest = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+            return normalize_path(&real.join(rest));
+        }
+    }
+    normalize_path(path)
+}
+
+/// Resolve `.` and `..` components without touching the filesystem.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// The outcome of evaluating the policy for a call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyDecision {
+    /// No rule objected to the call
+    Allow,
+    /// A rule denied the call
+    Deny {
+        /// The name of the rule that denied the call
+        rule: String,
+        /// Explanation suitable for the model
+        reason: String,
+    },
+}
+
+/// Evaluates policy rules before tool calls are dispatched.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PolicyEngine {
+    rules: Vec<PolicyRule>,
+}
+
+impl PolicyEngine {
+    /// Create an engine from a list of rules.
+    pub fn new(rules: Vec<PolicyRule>) -> Self {
+        Self { rules }
+    }
+
+    /// Parse a JSON array of rules.
+    pub fn from_json(json: &str) -> Result<Self, McpRigIntegrationError> {
+        let rules = serde_json::from_str(json)
+            .map_err(|e| McpRigIntegrationError::ConfigError(format!("invalid policy: {}", e)))?;
+        Ok(Self::new(rules))
+    }
+
+    /// Add a rule to the engine.
+    pub fn with_rule(mut self, rule: PolicyRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Get the rules in evaluation order
+    pub fn rules(&self) -> &[PolicyRule] {
+        &self.rules
+    }
+
+    /// Evaluate the rules for a call and log the decision.
+    ///
+    /// # Parameters
+    ///
+    /// - `tool_name`: The MCP name of the tool being called
+    /// - `args`: The arguments the tool will be called with
+    ///
+    /// # Returns
+    ///
+    /// `PolicyDecision::Deny` for the first rule that fails, otherwise `Allow`
+    pub fn evaluate(&self, tool_name: &str, args: &Value) -> PolicyDecision {
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !rule.applies_to(tool_name) {
+                continue;
+            }
+            if let Some(violation) = rule.check(args) {
+                let rule_name = rule
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("rule #{}", index + 1));
+                let reason = match &rule.reason {
+                    Some(reason) => format!("{} ({})", reason, violation),
+                    None => violation,
+                };
+                tracing::warn!(
+                    tool = tool_name,
+                    rule = %rule_name,
+                    reason = %reason,
+                    "policy denied tool call"
+                );
+                return PolicyDecision::Deny {
+                    rule: rule_name,
+                    reason,
+                };
+            }
+        }
+
+        tracing::info!(tool = tool_name, "policy allowed tool call");
+        PolicyDecision::Allow
+    }
+}
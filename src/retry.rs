@@ -0,0 +1,104 @@
+// src/retry.rs
+
+//! Retry policy for transient tool call failures.
+//!
+//! A `RetryPolicy` describes how many times a failed MCP tool call is
+//! attempted and how long to wait between attempts. Only errors classified as
+//! transient by `McpRigIntegrationError::is_transient` are retried; errors
+//! reported by the tool itself are returned immediately.
+
+use rand::Rng;
+use std::time::Duration;
+
+/// Configuration for retrying transient tool call failures.
+///
+/// Backoff grows exponentially from `initial_backoff` by `multiplier` per
+/// attempt, is capped at `max_backoff`, and is randomized by `jitter` so that
+/// concurrent callers don't retry in lockstep.
+///
+/// # Example
+///
+/// ```rust
+/// use mcp_rig::RetryPolicy;
+/// use std::time::Duration;
+///
+/// let policy = RetryPolicy::new(4)
+///     .with_backoff(Duration::from_millis(100), Duration::from_secs(2))
+///     .with_jitter(0.25);
+/// assert_eq!(policy.max_attempts, 4);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between attempts
+    pub max_backoff: Duration,
+    /// Factor applied to the delay after each attempt
+    pub multiplier: f64,
+    /// Fraction of the delay to randomize, between 0.0 and 1.0
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create a policy with the given number of attempts and default backoff.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            ..Self::default()
+        }
+    }
+
+    /// Create a policy that never retries.
+    pub fn none() -> Self {
+        Self::new(1)
+    }
+
+    /// Set the initial and maximum delay between attempts.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Set the factor applied to the delay after each attempt.
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Set the fraction of the delay to randomize.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Compute the delay to wait after the given failed attempt (starting at 1).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1) as i32;
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let capped = base.min(self.max_backoff.as_secs_f64());
+
+        let jittered = if self.jitter > 0.0 {
+            let factor = rand::thread_rng().gen_range(1.0 - self.jitter..=1.0 + self.jitter);
+            capped * factor
+        } else {
+            capped
+        };
+
+        Duration::from_secs_f64(jittered.max(0.0))
+    }
+}
@@ -0,0 +1,264 @@
+// src/rig_server.rs
+
+//! Rig tools and agents served over MCP.
+//!
+//! `RigServer` is the reverse of `McpToolAdapter`: it lists Rig tool
+//! definitions as MCP tools and answers `tools/call` with `Tool::call`. An
+//! agent can be added as a tool of its own that takes a prompt and returns
+//! the agent's answer, so other MCP hosts can hand work to it.
+//!
+//! Serve it with `serve_stdio`, or mount it into an `McpConnectionManager`.
+//! A failing tool becomes a result with `isError` set so the calling model
+//! sees what went wrong; unknown tools and bad parameters are JSON-RPC
+//! errors.
+
+use crate::chat::{run_turn, ChatEvent, DEFAULT_MAX_STEPS};
+use crate::server::{
+    invalid_params, method_not_found, negotiate_protocol_version, request_arguments, to_result,
+    McpServer,
+};
+use mcp_spec::{
+    protocol::{CallToolResult, ErrorData},
+    Content,
+};
+use rig::{
+    agent::Agent,
+    completion::{CompletionModel, ToolDefinition},
+    tool::ToolDyn,
+};
+use serde_json::{json, Value};
+
+/// Something `RigServer` can list and call.
+#[async_trait::async_trait]
+trait ServedTool: Send + Sync {
+    async fn definition(&self) -> ToolDefinition;
+
+    async fn call(&self, arguments: String) -> Result<String, String>;
+}
+
+/// A plain Rig tool.
+struct RigTool(Box<dyn ToolDyn>);
+
+#[async_trait::async_trait]
+impl ServedTool for RigTool {
+    async fn definition(&self) -> ToolDefinition {
+        self.0.definition(String::new()).await
+    }
+
+    async fn call(&self, arguments: String) -> Result<String, String> {
+        self.0.call(arguments).await.map_err(|e| e.to_string())
+    }
+}
+
+/// An agent answering a prompt, running its own tools as needed.
+struct AgentTool<M: CompletionModel> {
+    /// The tool name
+    name: String,
+    /// What the agent is good for, shown to the calling model
+    description: String,
+    /// The agent answering
+    agent: Agent<M>,
+    /// How many model requests one answer may take
+    max_steps: usize,
+}
+
+#[async_trait::async_trait]
+impl<M: CompletionModel + 'static> ServedTool for AgentTool<M> {
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "prompt": {
+                        "type": "string",
+                        "description": "The request for the agent, with everything it needs to know"
+                    }
+                },
+                "required": ["prompt"]
+            }),
+        }
+    }
+
+    async fn call(&self, arguments: String) -> Result<String, String> {
+        let arguments: Value = serde_json::from_str(&arguments).map_err(|e| e.to_string())?;
+        let prompt = arguments["prompt"]
+            .as_str()
+            .ok_or("missing string argument 'prompt'")?;
+
+        // Every call is a new conversation
+        let mut history = Vec::new();
+        let mut log =
+            |event: ChatEvent<'_>| tracing::debug!(agent = %self.name, ?event, "agent step");
+        run_turn(&self.agent, &mut history, prompt, self.max_steps, &mut log)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// An MCP server offering Rig tools and agents.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let server = RigServer::new("assistant", "1.0.0")
+///     .with_tool(Adder)
+///     .with_agent("researcher", "Answers questions about the codebase", agent);
+/// mcp_rig::serve_stdio(Arc::new(server)).await?;
+/// ```
+pub struct RigServer {
+    /// The server name reported to clients
+    name: String,
+    /// The server version reported to clients
+    version: String,
+    /// Usage hints for the client's model
+    instructions: Option<String>,
+    /// Model requests allowed per agent answer, for agents added afterwards
+    max_steps: usize,
+    /// The tools in the order they were added, by name
+    tools: Vec<(String, Box<dyn ServedTool>)>,
+}
+
+impl RigServer {
+    /// Create a server with no tools.
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            instructions: None,
+            max_steps: DEFAULT_MAX_STEPS,
+            tools: Vec::new(),
+        }
+    }
+
+    /// Set the instructions sent to clients when they connect.
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Set how many model requests an agent may take per answer.
+    ///
+    /// Applies to agents added after this call.
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps.max(1);
+        self
+    }
+
+    /// Offer a Rig tool. A tool with the same name is replaced.
+    pub fn with_tool(self, tool: impl ToolDyn + 'static) -> Self {
+        let name = tool.name();
+        self.with_served(name, Box::new(RigTool(Box::new(tool))))
+    }
+
+    /// Offer an agent as a tool that takes a `prompt` and returns the answer.
+    ///
+    /// Each call starts a new conversation. The agent's own tools run inside
+    /// the call and are not offered separately.
+    pub fn with_agent<M: CompletionModel + 'static>(
+        self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        agent: Agent<M>,
+    ) -> Self {
+        let name = name.into();
+        let tool = AgentTool {
+            name: name.clone(),
+            description: description.into(),
+            agent,
+            max_steps: self.max_steps,
+        };
+        self.with_served(name, Box::new(tool))
+    }
+
+    fn with_served(mut self, name: String, tool: Box<dyn ServedTool>) -> Self {
+        match self
+            .tools
+            .iter_mut()
+            .find(|(existing, _)| *existing == name)
+        {
+            Some(entry) => entry.1 = tool,
+            None => self.tools.push((name, tool)),
+        }
+        self
+    }
+
+    /// Get the names of the tools offered
+    pub fn tool_names(&self) -> Vec<&str> {
+        self.tools.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        let mut result = json!({
+            "protocolVersion": negotiate_protocol_version(params),
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": self.name, "version": self.version }
+        });
+        if let Some(instructions) = &self.instructions {
+            result["instructions"] = json!(instructions);
+        }
+        result
+    }
+
+    async fn list_tools(&self) -> Value {
+        let mut tools = Vec::new();
+        for (_, tool) in &self.tools {
+            let definition = tool.definition().await;
+            tools.push(mcp_spec::Tool::new(
+                definition.name,
+                definition.description,
+                definition.parameters,
+            ));
+        }
+        json!({ "tools": tools })
+    }
+
+    async fn call_tool(&self, params: Value) -> Result<Value, ErrorData> {
+        let name = params["name"]
+            .as_str()
+            .ok_or_else(|| invalid_params("missing tool name"))?;
+        let (_, tool) = self
+            .tools
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .ok_or_else(|| invalid_params(format!("Unknown tool: {}", name)))?;
+        let arguments = request_arguments(&params);
+
+        let result = match tool.call(arguments.to_string()).await {
+            Ok(output) => CallToolResult {
+                content: vec![Content::text(output_text(output))],
+                is_error: None,
+            },
+            Err(error) => {
+                tracing::debug!(tool = %name, %error, "served tool failed");
+                CallToolResult {
+                    content: vec![Content::text(error)],
+                    is_error: Some(true),
+                }
+            }
+        };
+        to_result(result)
+    }
+}
+
+#[async_trait::async_trait]
+impl McpServer for RigServer {
+    async fn handle_request(&self, method: &str, params: Value) -> Result<Value, ErrorData> {
+        match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(self.list_tools().await),
+            "tools/call" => self.call_tool(params).await,
+            _ => Err(method_not_found(method)),
+        }
+    }
+}
+
+/// Rig serializes tool output as JSON; unwrap plain strings.
+fn output_text(output: String) -> String {
+    match serde_json::from_str::<Value>(&output) {
+        Ok(Value::String(text)) => text,
+        _ => output,
+    }
+}
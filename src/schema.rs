@@ -0,0 +1,490 @@
+// src/schema.rs
+
+//! JSON Schema normalization for provider-specific tool schema limits.
+//!
+//! MCP servers describe tool parameters with whatever JSON Schema features
+//! they like, but LLM providers only accept a subset. This module rewrites a
+//! tool's `input_schema` for a given `SchemaProfile`: it inlines `$ref`s,
+//! drops or rewrites keywords the provider rejects, and, for OpenAI strict
+//! mode, marks every property as required and closes every object.
+//!
+//! Every change that loses information is reported so it can be logged and
+//! reviewed.
+
+use serde_json::{json, Map, Value};
+
+/// The provider whose schema rules a tool definition should follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchemaProfile {
+    /// Advertise the server's schema unchanged
+    #[default]
+    Passthrough,
+    /// OpenAI function calling without strict mode
+    OpenAi,
+    /// OpenAI function calling with `strict: true`
+    OpenAiStrict,
+    /// Google Gemini function declarations
+    Gemini,
+    /// Anthropic tool use
+    Anthropic,
+}
+
+impl SchemaProfile {
+    /// Keywords the provider rejects outright.
+    fn unsupported_keywords(self) -> &'static [&'static str] {
+        match self {
+            Self::Passthrough => &[],
+            Self::OpenAi => &["$schema", "$id"],
+            Self::OpenAiStrict => &[
+                "$schema",
+                "$id",
+                "format",
+                "default",
+                "examples",
+                "minLength",
+                "maxLength",
+                "pattern",
+                "minimum",
+                "maximum",
+                "exclusiveMinimum",
+                "exclusiveMaximum",
+                "multipleOf",
+                "minItems",
+                "maxItems",
+                "uniqueItems",
+                "minProperties",
+                "maxProperties",
+                "patternProperties",
+                "not",
+            ],
+            Self::Gemini => &[
+                "$schema",
+                "$id",
+                "additionalProperties",
+                "default",
+                "examples",
+                "const",
+                "exclusiveMinimum",
+                "exclusiveMaximum",
+                "multipleOf",
+                "patternProperties",
+                "uniqueItems",
+                "not",
+            ],
+            Self::Anthropic => &["$schema", "$id"],
+        }
+    }
+
+    /// Whether `$ref`s must be inlined for this provider.
+    fn inlines_refs(self) -> bool {
+        matches!(self, Self::OpenAiStrict | Self::Gemini)
+    }
+
+    /// Whether `allOf` branches must be merged into the parent schema.
+    fn merges_all_of(self) -> bool {
+        matches!(self, Self::OpenAiStrict | Self::Gemini)
+    }
+}
+
+/// The result of normalizing a schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedSchema {
+    /// The schema to advertise to the provider
+    pub schema: Value,
+    /// Human-readable descriptions of lossy transformations
+    pub lossy: Vec<String>,
+}
+
+/// Rewrite `schema` so that it is accepted by the given provider.
+///
+/// # Parameters
+///
+/// - `schema`: The tool's parameter schema, as returned by the MCP server
+/// - `profile`: The provider rules to apply
+///
+/// # Returns
+///
+/// The normalized schema and a list of transformations that lost information
+pub fn normalize_schema(schema: &Value, profile: SchemaProfile) -> NormalizedSchema {
+    if profile == SchemaProfile::Passthrough {
+        return NormalizedSchema {
+            schema: schema.clone(),
+            lossy: Vec::new(),
+        };
+    }
+
+    let mut normalizer = Normalizer {
+        root: schema,
+        profile,
+        lossy: Vec::new(),
+    };
+    let mut normalized = normalizer.normalize(schema, "", 0);
+
+    // Tool parameters must always be described by an object schema
+    if let Value::Object(map) = &mut normalized {
+        if map.get("type").and_then(Value::as_str) != Some("object") {
+            if map.contains_key("type") {
+                normalizer
+                    .lossy
+                    .push("(root): replaced non-object type with object".to_string());
+            }
+            map.insert("type".to_string(), json!("object"));
+        }
+        map.entry("properties").or_insert_with(|| json!({}));
+
+        if profile == SchemaProfile::Anthropic {
+            for keyword in ["oneOf", "anyOf", "allOf"] {
+                if map.remove(keyword).is_some() {
+                    normalizer
+                        .lossy
+                        .push(format!("(root): dropped top-level '{}'", keyword));
+                }
+            }
+        }
+        if profile == SchemaProfile::OpenAiStrict {
+            normalizer.close_object(map, "");
+        }
+    }
+
+    NormalizedSchema {
+        schema: normalized,
+        lossy: normalizer.lossy,
+    }
+}
+
+/// Remove `null` placeholders that strict mode forced onto optional properties.
+///
+/// In OpenAI strict mode every property is required, so optional properties
+/// are made nullable and the model sends `null` when it has no value. Servers
+/// generally expect those properties to be absent instead. This removes `null`
+/// members that the original `schema` neither requires nor allows to be null.
+pub fn strip_null_placeholders(schema: &Value, value: &mut Value) {
+    let Value::Object(object) = value else {
+        return;
+    };
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let properties = schema.get("properties").and_then(Value::as_object);
+
+    object.retain(|name, member| {
+        if !member.is_null() || required.contains(&name.as_str()) {
+            return true;
+        }
+        properties
+            .and_then(|p| p.get(name))
+            .is_some_and(allows_null)
+    });
+
+    if let Some(properties) = properties {
+        for (name, member) in object.iter_mut() {
+            if let Some(member_schema) = properties.get(name) {
+                strip_null_placeholders(member_schema, member);
+            }
+        }
+    }
+}
+
+fn allows_null(schema: &Value) -> bool {
+    match schema.get("type") {
+        Some(Value::String(t)) => t == "null",
+        Some(Value::Array(types)) => types.iter().any(|t| t == "null"),
+        _ => schema.get("nullable") == Some(&Value::Bool(true)),
+    }
+}
+
+fn location(path: &str) -> &str {
+    if path.is_empty() {
+        "(root)"
+    } else {
+        path
+    }
+}
+
+/// Maximum `$ref` inlining depth, to stop recursive schemas from expanding forever.
+const MAX_REF_DEPTH: usize = 16;
+
+struct Normalizer<'a> {
+    root: &'a Value,
+    profile: SchemaProfile,
+    lossy: Vec<String>,
+}
+
+impl Normalizer<'_> {
+    fn normalize(&mut self, schema: &Value, path: &str, depth: usize) -> Value {
+        let Value::Object(map) = schema else {
+            return schema.clone();
+        };
+
+        if self.profile.inlines_refs() {
+            if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+                let target = reference
+                    .strip_prefix('#')
+                    .and_then(|pointer| self.root.pointer(pointer));
+                return match target {
+                    Some(_) if depth >= MAX_REF_DEPTH => {
+                        self.lossy.push(format!(
+                            "{}: recursive reference '{}' truncated to an unconstrained schema",
+                            location(path),
+                            reference
+                        ));
+                        json!({})
+                    }
+                    Some(target) => {
+                        let target = target.clone();
+                        self.normalize(&target, path, depth + 1)
+                    }
+                    None => {
+                        self.lossy.push(format!(
+                            "{}: unresolvable reference '{}' replaced with an unconstrained schema",
+                            location(path),
+                            reference
+                        ));
+                        json!({})
+                    }
+                };
+            }
+        }
+
+        let mut out = Map::new();
+        // Merged once the parent's own members are in place, so they aren't overwritten
+        let mut all_of = None;
+        for (key, value) in map {
+            if self.profile.unsupported_keywords().contains(&key.as_str()) {
+                if key == "const" && self.profile == SchemaProfile::Gemini {
+                    out.insert("enum".to_string(), json!([value]));
+                    continue;
+                }
+                if !matches!(key.as_str(), "$schema" | "$id" | "examples" | "default") {
+                    self.lossy.push(format!(
+                        "{}: dropped unsupported keyword '{}'",
+                        location(path),
+                        key
+                    ));
+                }
+                continue;
+            }
+
+            match key.as_str() {
+                "$defs" | "definitions" if self.profile.inlines_refs() => {}
+                "properties" => {
+                    let properties = value
+                        .as_object()
+                        .map(|props| {
+                            props
+                                .iter()
+                                .map(|(name, prop)| {
+                                    let prop_path = format!("{}/properties/{}", path, name);
+                                    (name.clone(), self.normalize(prop, &prop_path, depth))
+                                })
+                                .collect::<Map<_, _>>()
+                        })
+                        .unwrap_or_default();
+                    out.insert(key.clone(), Value::Object(properties));
+                }
+                "items" | "additionalProperties" if value.is_object() => {
+                    let child_path = format!("{}/{}", path, key);
+                    out.insert(key.clone(), self.normalize(value, &child_path, depth));
+                }
+                "oneOf" | "anyOf" | "allOf" => {
+                    let branches: Vec<Value> = value
+                        .as_array()
+                        .map(|branches| {
+                            branches
+                                .iter()
+                                .enumerate()
+                                .map(|(i, b)| {
+                                    self.normalize(b, &format!("{}/{}/{}", path, key, i), depth)
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    if key == "allOf" && self.profile.merges_all_of() {
+                        all_of = Some(branches);
+                    } else {
+                        self.insert_combinator(&mut out, key, branches, path);
+                    }
+                }
+                "type" if self.profile == SchemaProfile::Gemini && value.is_array() => {
+                    // Gemini accepts a single type plus a `nullable` flag
+                    let types: Vec<&str> = value
+                        .as_array()
+                        .map(|t| t.iter().filter_map(Value::as_str).collect())
+                        .unwrap_or_default();
+                    let non_null: Vec<&str> =
+                        types.iter().copied().filter(|t| *t != "null").collect();
+                    if non_null.len() > 1 {
+                        self.lossy.push(format!(
+                            "{}: multiple types {:?} narrowed to '{}'",
+                            location(path),
+                            non_null,
+                            non_null[0]
+                        ));
+                    }
+                    if let Some(first) = non_null.first() {
+                        out.insert("type".to_string(), json!(first));
+                    }
+                    if types.contains(&"null") {
+                        out.insert("nullable".to_string(), json!(true));
+                    }
+                }
+                _ => {
+                    out.insert(key.clone(), value.clone());
+                }
+            }
+        }
+
+        if let Some(branches) = all_of {
+            self.insert_combinator(&mut out, "allOf", branches, path);
+        }
+        if self.profile == SchemaProfile::OpenAiStrict {
+            self.close_object(&mut out, path);
+        }
+        Value::Object(out)
+    }
+
+    fn insert_combinator(
+        &mut self,
+        out: &mut Map<String, Value>,
+        keyword: &str,
+        branches: Vec<Value>,
+        path: &str,
+    ) {
+        match (self.profile, keyword) {
+            (SchemaProfile::OpenAiStrict | SchemaProfile::Gemini, "oneOf") => {
+                self.lossy
+                    .push(format!("{}: 'oneOf' relaxed to 'anyOf'", location(path)));
+                self.insert_branches(out, "anyOf", branches, path);
+            }
+            (_, "allOf") if self.profile.merges_all_of() => {
+                // Merge the branches into the parent schema
+                for branch in branches {
+                    let Value::Object(branch) = branch else {
+                        continue;
+                    };
+                    for (key, value) in branch {
+                        match (out.get_mut(&key), value) {
+                            (Some(Value::Object(existing)), Value::Object(extra))
+                                if key == "properties" =>
+                            {
+                                existing.extend(extra);
+                            }
+                            (Some(Value::Array(existing)), Value::Array(extra))
+                                if key == "required" =>
+                            {
+                                for name in extra {
+                                    if !existing.contains(&name) {
+                                        existing.push(name);
+                                    }
+                                }
+                            }
+                            (Some(existing), value) if *existing != value => {
+                                self.lossy.push(format!(
+                                    "{}: conflicting '{}' in 'allOf' kept the first value",
+                                    location(path),
+                                    key
+                                ));
+                            }
+                            (Some(_), _) => {}
+                            (None, value) => {
+                                out.insert(key, value);
+                            }
+                        }
+                    }
+                }
+            }
+            _ => self.insert_branches(out, keyword, branches, path),
+        }
+    }
+
+    /// Insert a combinator, keeping an existing one with the same keyword.
+    fn insert_branches(
+        &mut self,
+        out: &mut Map<String, Value>,
+        keyword: &str,
+        branches: Vec<Value>,
+        path: &str,
+    ) {
+        if out.contains_key(keyword) {
+            self.lossy.push(format!(
+                "{}: dropped branches that would replace the existing '{}'",
+                location(path),
+                keyword
+            ));
+            return;
+        }
+        out.insert(keyword.to_string(), Value::Array(branches));
+    }
+
+    /// Apply OpenAI strict mode rules to an object schema.
+    ///
+    /// Every property becomes required, properties that were optional become
+    /// nullable, and `additionalProperties` is set to `false`.
+    fn close_object(&mut self, map: &mut Map<String, Value>, path: &str) {
+        let is_object = map.get("type").and_then(Value::as_str) == Some("object")
+            || map.contains_key("properties");
+        if !is_object {
+            return;
+        }
+
+        let required: Vec<String> = map
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|r| {
+                r.iter()
+                    .filter_map(Value::as_str)
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if let Some(Value::Object(properties)) = map.get_mut("properties") {
+            for (name, property) in properties.iter_mut() {
+                if !required.contains(name) {
+                    make_nullable(property);
+                }
+            }
+            let all: Vec<Value> = properties.keys().map(|k| json!(k)).collect();
+            map.insert("required".to_string(), Value::Array(all));
+        }
+
+        match map.insert("additionalProperties".to_string(), json!(false)) {
+            Some(Value::Bool(false)) | None => {}
+            Some(_) => self.lossy.push(format!(
+                "{}: additional properties are no longer allowed",
+                location(path)
+            )),
+        }
+    }
+}
+
+/// Allow `null` in addition to the schema's existing types.
+fn make_nullable(schema: &mut Value) {
+    let Value::Object(map) = schema else {
+        return;
+    };
+    match map.get("type").cloned() {
+        Some(Value::String(t)) if t != "null" => {
+            map.insert("type".to_string(), json!([t, "null"]));
+        }
+        Some(Value::Array(mut types)) => {
+            if !types.iter().any(|t| t == "null") {
+                types.push(json!("null"));
+                map.insert("type".to_string(), Value::Array(types));
+            }
+        }
+        Some(_) => {}
+        None => {
+            if let Some(Value::Array(branches)) = map.get_mut("anyOf") {
+                branches.push(json!({ "type": "null" }));
+            }
+        }
+    }
+    if let Some(Value::Array(values)) = map.get_mut("enum") {
+        if !values.contains(&Value::Null) {
+            values.push(Value::Null);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+// src/server.rs
+
+//! MCP servers implemented in Rust.
+//!
+//! An `McpServer` answers JSON-RPC requests directly, without a process or
+//! socket in between. Mount one into `McpConnectionManager::mount_server` to
+//! use it like any other MCP client; the messages still go through the
+//! regular client, request IDs and JSON-RPC envelopes.
+//!
+//! The same servers can be offered to other MCP hosts with `serve_stdio`,
+//! which speaks newline-delimited JSON-RPC on stdin and stdout.
+
+use crate::error::McpRigIntegrationError;
+use mcp_spec::protocol::{
+    ErrorData, JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse,
+    INTERNAL_ERROR, INVALID_PARAMS, METHOD_NOT_FOUND, PARSE_ERROR,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    sync::mpsc,
+};
+
+/// A Rust implementation of an MCP server.
+///
+/// Implementations handle every method themselves, including `initialize`.
+///
+/// # Example
+///
+/// ```rust
+/// use mcp_rig::{method_not_found, McpServer};
+/// use mcp_spec::protocol::ErrorData;
+/// use serde_json::{json, Value};
+///
+/// struct Clock;
+///
+/// #[async_trait::async_trait]
+/// impl McpServer for Clock {
+///     async fn handle_request(&self, method: &str, _params: Value) -> Result<Value, ErrorData> {
+///         match method {
+///             "initialize" => Ok(json!({
+///                 "protocolVersion": "2025-03-26",
+///                 "capabilities": { "tools": {} },
+///                 "serverInfo": { "name": "clock", "version": "0.1.0" }
+///             })),
+///             "tools/list" => Ok(json!({ "tools": [] })),
+///             _ => Err(method_not_found(method)),
+///         }
+///     }
+/// }
+/// ```
+#[async_trait::async_trait]
+pub trait McpServer: Send + Sync + 'static {
+    /// Handle a request and return its result.
+    async fn handle_request(&self, method: &str, params: Value) -> Result<Value, ErrorData>;
+
+    /// Handle a notification from the client. The default ignores it.
+    async fn handle_notification(&self, _method: &str, _params: Value) {}
+
+    /// Take the notifications the server sends to its client.
+    ///
+    /// Called once when serving starts. The default sends none.
+    fn take_notifications(&self) -> Option<mpsc::UnboundedReceiver<JsonRpcNotification>> {
+        None
+    }
+}
+
+/// Run a request through a server and wrap the outcome in a JSON-RPC reply.
+pub(crate) async fn dispatch(server: &dyn McpServer, request: JsonRpcRequest) -> JsonRpcMessage {
+    let params = request.params.unwrap_or(Value::Null);
+    match server.handle_request(&request.method, params).await {
+        Ok(result) => JsonRpcMessage::Response(JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: request.id,
+            result: Some(result),
+            error: None,
+        }),
+        Err(error) => JsonRpcMessage::Error(JsonRpcError {
+            jsonrpc: "2.0".to_string(),
+            id: request.id,
+            error,
+        }),
+    }
+}
+
+/// The protocol version servers in this crate answer with by default.
+pub const PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// The protocol versions servers in this crate implement, newest first.
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-03-26", "2024-11-05"];
+
+/// Pick the protocol version to answer an `initialize` request with.
+///
+/// The client's requested version is accepted if it is supported; otherwise
+/// the server offers `PROTOCOL_VERSION` and the client decides whether to
+/// continue.
+///
+/// ```rust
+/// use mcp_rig::{negotiate_protocol_version, PROTOCOL_VERSION};
+/// use serde_json::json;
+///
+/// let params = json!({ "protocolVersion": "2024-11-05" });
+/// assert_eq!(negotiate_protocol_version(&params), "2024-11-05");
+/// let params = json!({ "protocolVersion": "1999-01-01" });
+/// assert_eq!(negotiate_protocol_version(&params), PROTOCOL_VERSION);
+/// ```
+pub fn negotiate_protocol_version(params: &Value) -> &'static str {
+    let requested = params["protocolVersion"].as_str();
+    SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .find(|version| Some(**version) == requested)
+        .copied()
+        .unwrap_or(PROTOCOL_VERSION)
+}
+
+/// The error returned for methods a server does not implement.
+pub fn method_not_found(method: &str) -> ErrorData {
+    ErrorData {
+        code: METHOD_NOT_FOUND,
+        message: format!("Method not found: {}", method),
+        data: None,
+    }
+}
+
+/// The error returned for requests with missing or unknown parameters.
+pub fn invalid_params(message: impl Into<String>) -> ErrorData {
+    ErrorData {
+        code: INVALID_PARAMS,
+        message: message.into(),
+        data: None,
+    }
+}
+
+/// Get the `arguments` of a call or prompt request, defaulting to `{}`.
+pub(crate) fn request_arguments(params: &Value) -> Value {
+    match &params["arguments"] {
+        Value::Null => json!({}),
+        arguments => arguments.clone(),
+    }
+}
+
+/// Serialize a result, reporting failure as an internal error.
+pub(crate) fn to_result(result: impl Serialize) -> Result<Value, ErrorData> {
+    serde_json::to_value(result).map_err(|e| ErrorData {
+        code: INTERNAL_ERROR,
+        message: e.to_string(),
+        data: None,
+    })
+}
+
+/// Serve `server` on stdin and stdout until stdin closes.
+///
+/// Nothing else may write to stdout while serving; send logs to stderr.
+pub async fn serve_stdio(server: Arc<dyn McpServer>) -> Result<(), McpRigIntegrationError> {
+    serve_stream(server, tokio::io::stdin(), tokio::io::stdout()).await
+}
+
+/// Serve `server` with newline-delimited JSON-RPC over a pair of streams.
+///
+/// Requests are handled concurrently and replies are written as they finish;
+/// notifications are handled in order. Notifications from the server are
+/// written between replies. Returns once `reader` reaches the end and every
+/// request in flight has been answered.
+pub async fn serve_stream<R, W>(
+    server: Arc<dyn McpServer>,
+    reader: R,
+    mut writer: W,
+) -> Result<(), McpRigIntegrationError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (replies, mut outgoing) = mpsc::unbounded_channel::<JsonRpcMessage>();
+    let write_task = tokio::spawn(async move {
+        while let Some(reply) = outgoing.recv().await {
+            let mut line = serde_json::to_vec(&reply)?;
+            line.push(b'\n');
+            writer.write_all(&line).await.map_err(io_error)?;
+            writer.flush().await.map_err(io_error)?;
+        }
+        Ok::<_, McpRigIntegrationError>(())
+    });
+    let forward_task = server.take_notifications().map(|mut notifications| {
+        let replies = replies.clone();
+        tokio::spawn(async move {
+            while let Some(notification) = notifications.recv().await {
+                if replies
+                    .send(JsonRpcMessage::Notification(notification))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        })
+    });
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await.map_err(io_error)? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message = match serde_json::from_str::<JsonRpcMessage>(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                let _ = replies.send(JsonRpcMessage::Error(JsonRpcError {
+                    jsonrpc: "2.0".to_string(),
+                    id: None,
+                    error: ErrorData {
+                        code: PARSE_ERROR,
+                        message: format!("Parse error: {}", e),
+                        data: None,
+                    },
+                }));
+                continue;
+            }
+        };
+        match message {
+            JsonRpcMessage::Request(request) => {
+                let server = Arc::clone(&server);
+                let replies = replies.clone();
+                tokio::spawn(async move {
+                    let _ = replies.send(dispatch(server.as_ref(), request).await);
+                });
+            }
+            JsonRpcMessage::Notification(notification) => {
+                let params = notification.params.unwrap_or_default();
+                server
+                    .handle_notification(&notification.method, params)
+                    .await;
+            }
+            other => tracing::debug!(message = ?other, "ignoring client message"),
+        }
+    }
+
+    // The writer stops once the last request in flight has replied
+    if let Some(task) = forward_task {
+        task.abort();
+        let _ = task.await;
+    }
+    drop(replies);
+    write_task
+        .await
+        .map_err(|e| McpRigIntegrationError::Other(e.to_string()))?
+}
+
+fn io_error(e: std::io::Error) -> McpRigIntegrationError {
+    McpRigIntegrationError::TransportError(e.to_string())
+}
@@ -3,16 +3,19 @@
 //! Functions for registering MCP tools with Rig and creating toolsets.
 //!
 //! This module exposes two main functions:
-//! - `create_mcp_toolset`: Creates a ToolSet from MCP tools for use with RAG
+//! - `create_mcp_toolset`: Creates a ToolSet from MCP tools for RAG-enabled
+//!   dynamic tool retrieval
 //! - `register_mcp_tools`: Registers MCP tools with a Rig agent builder
 //!
-//! This module provides the core functionality for integrating MCP tools with
-//! Rig agents. It includes functions for registering tools with agent builders
-//! and creating toolsets for RAG-enabled dynamic tool retrieval.
+//! Both have `_with` variants that pass each adapter through a closure so
+//! per-tool settings can be applied before registration, and that take a
+//! `ToolNameMap` so tools from several clients get distinct names.
 
 use crate::adapter::{McpToolAdapter, McpToolState};
 use crate::error::McpRigIntegrationError;
+use crate::naming::ToolNameMap;
 use mcp_client::McpClientTrait;
+use mcp_spec::Tool;
 use rig::{agent::AgentBuilder, completion::CompletionModel, tool::ToolSet};
 use std::sync::Arc;
 
@@ -31,24 +34,69 @@ use std::sync::Arc;
 ///
 /// `Ok(())` if registration was successful, or an error if it failed
 pub async fn register_mcp_tools<M: CompletionModel>(
-    mcp_client: Arc<Box<dyn McpClientTrait>>,
+    mcp_client: Arc<dyn McpClientTrait>,
     agent_builder: &mut AgentBuilder<M>,
     model: M,
 ) -> Result<(), McpRigIntegrationError> {
+    let mut names = ToolNameMap::default();
+    register_mcp_tools_with(mcp_client, agent_builder, model, &mut names, |adapter| {
+        adapter
+    })
+    .await
+}
+
+/// Register all available MCP tools with a Rig agent builder, configuring each adapter.
+///
+/// This works like `register_mcp_tools`, but passes every adapter through
+/// `configure` before registering it. Use it to apply per-client or per-tool
+/// settings such as timeouts and cancellation.
+///
+/// Exposed tool names are allocated from `names`. Pass the same map for
+/// every client registered with one agent, so that two servers offering a
+/// tool of the same name don't collide. A name set by `configure` with
+/// `with_exposed_name` is checked against the map too.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut names = ToolNameMap::default();
+/// let canceller = manager.get_canceller("git-client").unwrap();
+/// register_mcp_tools_with(git_client, &mut agent_builder, model, &mut names, |adapter| {
+///     let adapter = adapter.with_canceller(Arc::clone(&canceller));
+///     match adapter.tool_name() {
+///         "git_log" => adapter.with_timeout(Duration::from_secs(60)),
+///         _ => adapter.with_timeout(Duration::from_secs(10)),
+///     }
+/// })
+/// .await?;
+/// ```
+pub async fn register_mcp_tools_with<M, F>(
+    mcp_client: Arc<dyn McpClientTrait>,
+    agent_builder: &mut AgentBuilder<M>,
+    model: M,
+    names: &mut ToolNameMap,
+    configure: F,
+) -> Result<(), McpRigIntegrationError>
+where
+    M: CompletionModel,
+    F: Fn(McpToolAdapter) -> McpToolAdapter,
+{
     // List all available tools from the MCP client
-    let tools_list = mcp_client
-        .list_tools(None)
-        .await
-        .map_err(|e| McpRigIntegrationError::McpError(e.to_string()))?;
+    let tools = list_all_tools(mcp_client.as_ref()).await?;
 
     // For each tool, create an adapter and register it with the Rig agent
-    for tool in tools_list.tools {
-        let adapter = McpToolAdapter::new(
-            Arc::clone(&mcp_client),
-            tool.name,
-            tool.description,
-            tool.input_schema, // Changed from parameters to input_schema
+    for tool in tools {
+        let exposed_name = names.insert_distinct(&tool.name)?;
+        let adapter = configure(
+            McpToolAdapter::new(
+                Arc::clone(&mcp_client),
+                tool.name,
+                tool.description,
+                tool.input_schema, // Changed from parameters to input_schema
+            )
+            .with_exposed_name(exposed_name.clone()),
         );
+        check_exposed_name(names, &exposed_name, &adapter)?;
 
         let builder = std::mem::replace(agent_builder, AgentBuilder::new(model.clone()));
         *agent_builder = builder.tool(adapter);
@@ -59,31 +107,83 @@ pub async fn register_mcp_tools<M: CompletionModel>(
 
 /// Create a ToolSet from all available MCP tools for use with RAG
 pub async fn create_mcp_toolset(
-    mcp_client: Arc<Box<dyn McpClientTrait>>,
+    mcp_client: Arc<dyn McpClientTrait>,
 ) -> Result<ToolSet, McpRigIntegrationError> {
+    create_mcp_toolset_with(mcp_client, &mut ToolNameMap::default(), |adapter| adapter).await
+}
+
+/// Create a ToolSet from all available MCP tools, configuring each adapter
+///
+/// Exposed tool names are allocated from `names`, which can be shared
+/// between the toolsets of several clients.
+pub async fn create_mcp_toolset_with<F>(
+    mcp_client: Arc<dyn McpClientTrait>,
+    names: &mut ToolNameMap,
+    configure: F,
+) -> Result<ToolSet, McpRigIntegrationError>
+where
+    F: Fn(McpToolAdapter) -> McpToolAdapter,
+{
     let mut toolset = ToolSet::default();
 
     // List all available tools from the MCP client
-    let tools_list = mcp_client
-        .list_tools(None)
-        .await
-        .map_err(|e| McpRigIntegrationError::McpError(e.to_string()))?;
+    let tools = list_all_tools(mcp_client.as_ref()).await?;
 
     // For each tool, create a state and add it to the toolset
-    for tool in tools_list.tools {
+    for tool in tools {
+        let exposed_name = names.insert_distinct(&tool.name)?;
         let state = McpToolState {
             name: tool.name,
             description: tool.description,
             parameters: tool.input_schema, // Changed from parameters to input_schema
         };
 
-        toolset.add_tool(McpToolAdapter::new(
-            Arc::clone(&mcp_client),
-            state.name.clone(),
-            state.description.clone(),
-            state.parameters.clone(),
-        ));
+        let adapter = configure(
+            McpToolAdapter::new(
+                Arc::clone(&mcp_client),
+                state.name.clone(),
+                state.description.clone(),
+                state.parameters.clone(),
+            )
+            .with_exposed_name(exposed_name.clone()),
+        );
+        check_exposed_name(names, &exposed_name, &adapter)?;
+        toolset.add_tool(adapter);
     }
 
     Ok(toolset)
 }
+
+/// Record a name set by `configure` in place of the allocated one.
+///
+/// Fails if another tool already has the new name.
+fn check_exposed_name(
+    names: &mut ToolNameMap,
+    allocated: &str,
+    adapter: &McpToolAdapter,
+) -> Result<(), McpRigIntegrationError> {
+    if adapter.exposed_name() == allocated {
+        return Ok(());
+    }
+    names.remove(allocated);
+    names.reserve(adapter.tool_name(), adapter.exposed_name())
+}
+
+/// List every tool offered by an MCP client, following pagination cursors.
+pub(crate) async fn list_all_tools(
+    mcp_client: &dyn McpClientTrait,
+) -> Result<Vec<Tool>, McpRigIntegrationError> {
+    let mut tools = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = mcp_client
+            .list_tools(cursor)
+            .await
+            .map_err(|e| McpRigIntegrationError::McpError(e.to_string()))?;
+        tools.extend(page.tools);
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(tools),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+// src/transport/channel.rs
+
+//! In-process channel transport.
+//!
+//! `ChannelTransport::pair` creates two connected endpoints backed by tokio
+//! mpsc channels: the transport, which is handed to
+//! `McpConnectionManager::add_client`, and a `ServerChannel` for the server
+//! side. The server side can run an `McpServer` with `ServerChannel::serve`
+//! or be driven by hand with `recv` and `send`, which is handy in tests.
+
+use crate::notifications::{deliver, NotificationSink};
+use crate::server::{dispatch, McpServer};
+use mcp_client::transport::{Error, PendingRequests, Transport, TransportHandle};
+use mcp_spec::protocol::JsonRpcMessage;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+
+/// Client end of an in-process connection.
+///
+/// The transport can be started once. The connection ends when every
+/// handle, or the `ServerChannel`, is dropped.
+pub struct ChannelTransport {
+    /// Messages to the server
+    outgoing: mpsc::Sender<JsonRpcMessage>,
+    /// Messages from the server, taken by `start`
+    incoming: Mutex<Option<mpsc::Receiver<JsonRpcMessage>>>,
+    /// Where the server's notifications go
+    notifications: Option<NotificationSink>,
+}
+
+impl ChannelTransport {
+    /// Create a connected transport and server channel.
+    ///
+    /// # Parameters
+    ///
+    /// - `buffer`: How many messages each direction can hold before senders wait
+    pub fn pair(buffer: usize) -> (Self, ServerChannel) {
+        let (client_tx, server_rx) = mpsc::channel(buffer);
+        let (server_tx, client_rx) = mpsc::channel(buffer);
+        let transport = Self {
+            outgoing: client_tx,
+            incoming: Mutex::new(Some(client_rx)),
+            notifications: None,
+        };
+        let server = ServerChannel {
+            incoming: server_rx,
+            outgoing: server_tx,
+        };
+        (transport, server)
+    }
+
+    /// Deliver the server's notifications to `sink`.
+    pub fn with_notifications(mut self, sink: NotificationSink) -> Self {
+        self.notifications = Some(sink);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for ChannelTransport {
+    type Handle = ChannelHandle;
+
+    async fn start(&self) -> Result<Self::Handle, Error> {
+        let mut incoming = self
+            .incoming
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or(Error::NotConnected)?;
+
+        let pending = Arc::new(PendingRequests::new());
+        let router = Arc::clone(&pending);
+        let notifications = self.notifications.clone();
+        tokio::spawn(async move {
+            while let Some(message) = incoming.recv().await {
+                match message {
+                    JsonRpcMessage::Response(ref response) => {
+                        if let Some(id) = response.id {
+                            router.respond(&id.to_string(), Ok(message)).await;
+                        }
+                    }
+                    JsonRpcMessage::Error(ref error) => {
+                        if let Some(id) = error.id {
+                            router.respond(&id.to_string(), Ok(message)).await;
+                        }
+                    }
+                    JsonRpcMessage::Notification(notification) => {
+                        deliver(notifications.as_ref(), notification)
+                    }
+                    other => tracing::debug!(message = ?other, "ignoring server message"),
+                }
+            }
+            // The server is gone; fail the requests still waiting
+            router.clear().await;
+        });
+
+        Ok(ChannelHandle {
+            outgoing: self.outgoing.clone(),
+            pending,
+        })
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Handle for sending messages to an in-process server.
+#[derive(Clone)]
+pub struct ChannelHandle {
+    outgoing: mpsc::Sender<JsonRpcMessage>,
+    pending: Arc<PendingRequests>,
+}
+
+#[async_trait::async_trait]
+impl TransportHandle for ChannelHandle {
+    async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, Error> {
+        match &message {
+            JsonRpcMessage::Request(request) => {
+                let id = request.id.ok_or(Error::UnsupportedMessage)?;
+                let (respond_to, response) = oneshot::channel();
+                self.pending.insert(id.to_string(), respond_to).await;
+                self.outgoing
+                    .send(message)
+                    .await
+                    .map_err(|_| Error::ChannelClosed)?;
+                response.await.map_err(|_| Error::ChannelClosed)?
+            }
+            JsonRpcMessage::Notification(_) => {
+                self.outgoing
+                    .send(message)
+                    .await
+                    .map_err(|_| Error::ChannelClosed)?;
+                Ok(JsonRpcMessage::Nil)
+            }
+            _ => Err(Error::UnsupportedMessage),
+        }
+    }
+}
+
+/// Server end of an in-process connection.
+pub struct ServerChannel {
+    /// Messages from the client
+    incoming: mpsc::Receiver<JsonRpcMessage>,
+    /// Messages to the client
+    outgoing: mpsc::Sender<JsonRpcMessage>,
+}
+
+impl ServerChannel {
+    /// Receive the next message from the client, or `None` once it disconnects
+    pub async fn recv(&mut self) -> Option<JsonRpcMessage> {
+        self.incoming.recv().await
+    }
+
+    /// Send a message to the client
+    pub async fn send(&self, message: JsonRpcMessage) -> Result<(), Error> {
+        self.outgoing
+            .send(message)
+            .await
+            .map_err(|_| Error::ChannelClosed)
+    }
+
+    /// Answer the client's messages with `server` until it disconnects.
+    ///
+    /// Requests are handled concurrently, so a slow tool call does not hold
+    /// up the others; notifications are handled in order. Notifications
+    /// from the server are passed on to the client.
+    pub async fn serve(mut self, server: Arc<dyn McpServer>) {
+        if let Some(mut notifications) = server.take_notifications() {
+            let outgoing = self.outgoing.clone();
+            tokio::spawn(async move {
+                while let Some(notification) = notifications.recv().await {
+                    let message = JsonRpcMessage::Notification(notification);
+                    if outgoing.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        while let Some(message) = self.incoming.recv().await {
+            match message {
+                JsonRpcMessage::Request(request) => {
+                    let server = Arc::clone(&server);
+                    let outgoing = self.outgoing.clone();
+                    tokio::spawn(async move {
+                        let reply = dispatch(server.as_ref(), request).await;
+                        let _ = outgoing.send(reply).await;
+                    });
+                }
+                JsonRpcMessage::Notification(notification) => {
+                    let params = notification.params.unwrap_or_default();
+                    server
+                        .handle_notification(&notification.method, params)
+                        .await;
+                }
+                other => tracing::debug!(message = ?other, "ignoring client message"),
+            }
+        }
+    }
+}
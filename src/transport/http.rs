@@ -0,0 +1,692 @@
+// src/transport/http.rs
+
+//! Streamable HTTP transport.
+//!
+//! Newer MCP servers expose a single endpoint: every JSON-RPC message is
+//! POSTed to it, and the server answers requests either with a JSON body or
+//! with an SSE stream that ends with the response. The server may assign a
+//! session ID in the `Mcp-Session-Id` header, which is sent back on every
+//! later message.
+//!
+//! The transport recovers from interruptions where it can:
+//!
+//! - an SSE response stream that drops before the response arrives is
+//!   resumed with a GET carrying `Last-Event-ID`
+//! - an expired session (HTTP 404) is re-initialized by replaying the
+//!   original `initialize` handshake, then the message is retried
+//!
+//! Servers that reject the initial POST with 400, 404 or 405 only speak the
+//! legacy HTTP+SSE transport; unless disabled, the handle then falls back to
+//! it on the same URL: a GET opens an event stream that announces where
+//! messages are POSTed and carries the replies.
+//!
+//! With an `AuthProvider`, the `Authorization` header is fetched for every
+//! request so refreshed tokens are picked up. A 401 invalidates the token
+//! and the message is retried once. The legacy fallback sends the same
+//! headers and authorization.
+
+use crate::auth::AuthProvider;
+use crate::notifications::{deliver, NotificationSink};
+use futures::StreamExt;
+use mcp_client::transport::{Error, PendingRequests, Transport, TransportHandle};
+use mcp_spec::protocol::{JsonRpcMessage, JsonRpcNotification};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+    RequestBuilder, Response, StatusCode,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::{
+    sync::{oneshot, OnceCell},
+    task::JoinHandle,
+};
+
+/// Header carrying the session ID assigned by the server.
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// How many times a dropped SSE response stream is resumed.
+const MAX_STREAM_RESUMES: usize = 3;
+
+/// How long a legacy server has to announce its message endpoint.
+const LEGACY_ENDPOINT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The session ID of a Streamable HTTP connection.
+///
+/// Clones share the same value, so a copy taken before the client connects
+/// shows the ID the server assigns. Persist it to resume the session later
+/// with `StreamableHttpTransport::with_session_id`.
+#[derive(Debug, Clone, Default)]
+pub struct HttpSession(Arc<RwLock<Option<String>>>);
+
+impl HttpSession {
+    /// Get the current session ID, if the server assigned one
+    pub fn id(&self) -> Option<String> {
+        self.0.read().unwrap().clone()
+    }
+
+    fn set(&self, id: Option<String>) {
+        *self.0.write().unwrap() = id;
+    }
+}
+
+/// Transport for servers using the Streamable HTTP transport.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let transport = StreamableHttpTransport::new("http://localhost:8000/mcp")
+///     .with_header("Authorization", "Bearer secret");
+/// manager.add_client("remote".to_string(), transport, client_info).await?;
+/// ```
+#[derive(Clone)]
+pub struct StreamableHttpTransport {
+    /// The MCP endpoint
+    url: String,
+    /// Extra headers sent with every request
+    headers: HashMap<String, String>,
+    /// The session ID, shared with the handle
+    session: HttpSession,
+    /// Whether to fall back to legacy HTTP+SSE
+    sse_fallback: bool,
+    /// Supplies the `Authorization` header
+    auth: Option<Arc<dyn AuthProvider>>,
+    /// Where the server's notifications go
+    notifications: Option<NotificationSink>,
+}
+
+impl StreamableHttpTransport {
+    /// Create a transport for the endpoint at `url`.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            headers: HashMap::new(),
+            session: HttpSession::default(),
+            sse_fallback: true,
+            auth: None,
+            notifications: None,
+        }
+    }
+
+    /// Send a header with every request.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Send several headers with every request.
+    pub fn with_headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Resume an existing session instead of waiting for the server to assign one.
+    pub fn with_session_id(self, session_id: impl Into<String>) -> Self {
+        self.session.set(Some(session_id.into()));
+        self
+    }
+
+    /// Enable or disable falling back to the legacy HTTP+SSE transport.
+    pub fn with_sse_fallback(mut self, enabled: bool) -> Self {
+        self.sse_fallback = enabled;
+        self
+    }
+
+    /// Authorize every request with a header from `provider`.
+    pub fn with_auth(mut self, provider: Arc<dyn AuthProvider>) -> Self {
+        self.auth = Some(provider);
+        self
+    }
+
+    /// Deliver the notifications the server sends on response streams to `sink`.
+    pub fn with_notifications(mut self, sink: NotificationSink) -> Self {
+        self.notifications = Some(sink);
+        self
+    }
+
+    /// Get the session shared with handles created by this transport
+    pub fn session(&self) -> HttpSession {
+        self.session.clone()
+    }
+
+    fn header_map(&self) -> Result<HeaderMap, Error> {
+        let mut map = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let invalid = |e: &dyn std::fmt::Display| Error::HttpError {
+                status: 0,
+                message: format!("invalid header '{}': {}", name, e),
+            };
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(&e))?;
+            let value = HeaderValue::from_str(value).map_err(|e| invalid(&e))?;
+            map.insert(name, value);
+        }
+        Ok(map)
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for StreamableHttpTransport {
+    type Handle = StreamableHttpHandle;
+
+    async fn start(&self) -> Result<Self::Handle, Error> {
+        Ok(StreamableHttpHandle {
+            inner: Arc::new(HttpInner {
+                client: reqwest::Client::new(),
+                url: self.url.clone(),
+                headers: self.header_map()?,
+                session: self.session.clone(),
+                sse_fallback: self.sse_fallback,
+                auth: self.auth.clone(),
+                notifications: self.notifications.clone(),
+                legacy: OnceCell::new(),
+                handshake: RwLock::new(None),
+            }),
+        })
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        // Ask the server to end the session; servers may refuse with 405
+        let Some(session_id) = self.session.id() else {
+            return Ok(());
+        };
+        let request = reqwest::Client::new()
+            .delete(&self.url)
+            .headers(self.header_map()?)
+            .header(SESSION_HEADER, session_id);
+        let response = authorize(request, self.auth.as_deref())
+            .await?
+            .send()
+            .await
+            .map_err(http_error)?;
+        if !response.status().is_success() && response.status() != StatusCode::METHOD_NOT_ALLOWED {
+            tracing::debug!(status = %response.status(), "server did not end the MCP session");
+        }
+        self.session.set(None);
+        Ok(())
+    }
+}
+
+struct HttpInner {
+    client: reqwest::Client,
+    url: String,
+    headers: HeaderMap,
+    session: HttpSession,
+    sse_fallback: bool,
+    auth: Option<Arc<dyn AuthProvider>>,
+    notifications: Option<NotificationSink>,
+    /// Set when the server turned out to only support legacy HTTP+SSE
+    legacy: OnceCell<LegacySse>,
+    /// The `initialize` request, replayed when the session expires
+    handshake: RwLock<Option<JsonRpcMessage>>,
+}
+
+/// Handle for sending messages over Streamable HTTP.
+#[derive(Clone)]
+pub struct StreamableHttpHandle {
+    inner: Arc<HttpInner>,
+}
+
+impl StreamableHttpHandle {
+    /// Get the session ID assigned by the server
+    pub fn session_id(&self) -> Option<String> {
+        self.inner.session.id()
+    }
+
+    /// Check whether the handle fell back to the legacy HTTP+SSE transport
+    pub fn is_legacy(&self) -> bool {
+        self.inner.legacy.initialized()
+    }
+
+    async fn post(&self, message: &JsonRpcMessage) -> Result<Response, Error> {
+        let mut request = self
+            .inner
+            .client
+            .post(&self.inner.url)
+            .headers(self.inner.headers.clone())
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(message);
+        if let Some(session_id) = self.inner.session.id() {
+            request = request.header(SESSION_HEADER, session_id);
+        }
+        let request = authorize(request, self.inner.auth.as_deref()).await?;
+        let response = request.send().await.map_err(http_error)?;
+
+        if let Some(session_id) = response
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|v| v.to_str().ok())
+        {
+            self.inner.session.set(Some(session_id.to_string()));
+        }
+        Ok(response)
+    }
+
+    /// POST a message and read the reply, without session recovery.
+    async fn exchange(&self, message: &JsonRpcMessage) -> Result<JsonRpcMessage, Error> {
+        let response = self.post(message).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::HttpError {
+                status: status.as_u16(),
+                message: response.text().await.unwrap_or_else(|_| status.to_string()),
+            });
+        }
+
+        let request_id = match message {
+            JsonRpcMessage::Request(request) => request.id,
+            _ => return Ok(JsonRpcMessage::Nil),
+        };
+
+        let is_stream = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        if is_stream {
+            self.read_stream(response, request_id).await
+        } else {
+            let body: serde_json::Value = response.json().await.map_err(http_error)?;
+            // The server may batch the response with other messages
+            let messages = match body {
+                serde_json::Value::Array(items) => items,
+                single => vec![single],
+            };
+            let mut reply = None;
+            for message in messages
+                .into_iter()
+                .filter_map(|m| serde_json::from_value::<JsonRpcMessage>(m).ok())
+            {
+                match message {
+                    JsonRpcMessage::Notification(notification) => {
+                        deliver(self.inner.notifications.as_ref(), notification)
+                    }
+                    message if reply.is_none() && response_id(&message) == request_id => {
+                        reply = Some(message)
+                    }
+                    _ => {}
+                }
+            }
+            reply.ok_or_else(|| Error::HttpError {
+                status: status.as_u16(),
+                message: "response did not contain a reply to the request".to_string(),
+            })
+        }
+    }
+
+    /// Read an SSE response stream until the reply to `request_id` arrives.
+    async fn read_stream(
+        &self,
+        response: Response,
+        request_id: Option<u64>,
+    ) -> Result<JsonRpcMessage, Error> {
+        let mut response = response;
+        let mut last_event_id: Option<String> = None;
+        let mut resumes = 0;
+
+        loop {
+            let mut parser = SseParser::default();
+            let mut body = response.bytes_stream();
+            while let Some(chunk) = body.next().await {
+                let Ok(chunk) = chunk else {
+                    break;
+                };
+                for event in parser.feed(&chunk) {
+                    if event.id.is_some() {
+                        last_event_id = event.id.clone();
+                    }
+                    if event.event != "message" {
+                        continue;
+                    }
+                    match serde_json::from_str::<JsonRpcMessage>(&event.data) {
+                        Ok(message) if response_id(&message) == request_id => return Ok(message),
+                        Ok(JsonRpcMessage::Notification(notification)) => {
+                            deliver(self.inner.notifications.as_ref(), notification)
+                        }
+                        Ok(other) => {
+                            tracing::debug!(message = ?other, "ignoring server message on response stream")
+                        }
+                        Err(e) => tracing::warn!("failed to parse SSE message: {}", e),
+                    }
+                }
+            }
+
+            // The stream ended without the reply; resume it if the server gave event IDs
+            let Some(event_id) = last_event_id.clone() else {
+                return Err(Error::SseConnection(
+                    "response stream ended before the reply arrived".to_string(),
+                ));
+            };
+            if resumes == MAX_STREAM_RESUMES {
+                return Err(Error::SseConnection(format!(
+                    "response stream dropped {} times",
+                    resumes
+                )));
+            }
+            resumes += 1;
+            tracing::debug!(last_event_id = %event_id, attempt = resumes, "resuming SSE response stream");
+
+            let mut request = self
+                .inner
+                .client
+                .get(&self.inner.url)
+                .headers(self.inner.headers.clone())
+                .header(ACCEPT, "text/event-stream")
+                .header("last-event-id", event_id);
+            if let Some(session_id) = self.inner.session.id() {
+                request = request.header(SESSION_HEADER, session_id);
+            }
+            let request = authorize(request, self.inner.auth.as_deref()).await?;
+            response = request.send().await.map_err(http_error)?;
+            if !response.status().is_success() {
+                return Err(Error::HttpError {
+                    status: response.status().as_u16(),
+                    message: "failed to resume response stream".to_string(),
+                });
+            }
+        }
+    }
+
+    /// Start a new session by replaying the recorded handshake.
+    async fn reinitialize(&self) -> Result<(), Error> {
+        let handshake = self.inner.handshake.read().unwrap().clone();
+        let Some(initialize) = handshake else {
+            return Err(Error::NotConnected);
+        };
+        tracing::info!(url = %self.inner.url, "MCP session expired, re-initializing");
+
+        self.inner.session.set(None);
+        self.exchange(&initialize).await?;
+        let initialized = JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: "notifications/initialized".to_string(),
+            params: None,
+        });
+        self.exchange(&initialized).await?;
+        Ok(())
+    }
+
+    /// Switch to the legacy HTTP+SSE transport on the same URL.
+    async fn fall_back(&self) -> Result<&LegacySse, Error> {
+        self.inner
+            .legacy
+            .get_or_try_init(|| async {
+                tracing::info!(
+                    url = %self.inner.url,
+                    "server does not support Streamable HTTP, falling back to HTTP+SSE"
+                );
+                self.open_legacy().await
+            })
+            .await
+    }
+
+    /// Open the legacy event stream and wait for the message endpoint.
+    async fn open_legacy(&self) -> Result<LegacySse, Error> {
+        let request = self
+            .inner
+            .client
+            .get(&self.inner.url)
+            .headers(self.inner.headers.clone())
+            .header(ACCEPT, "text/event-stream");
+        let request = authorize(request, self.inner.auth.as_deref()).await?;
+        let response = request.send().await.map_err(http_error)?;
+        if !response.status().is_success() {
+            return Err(Error::HttpError {
+                status: response.status().as_u16(),
+                message: "failed to open the legacy SSE stream".to_string(),
+            });
+        }
+
+        let base = url::Url::parse(&self.inner.url)
+            .map_err(|e| Error::SseConnection(format!("invalid URL: {}", e)))?;
+        let pending = Arc::new(PendingRequests::new());
+        let (endpoint_tx, endpoint_rx) = oneshot::channel();
+        let reader = tokio::spawn(read_legacy_stream(
+            response,
+            base,
+            Arc::clone(&pending),
+            self.inner.notifications.clone(),
+            endpoint_tx,
+        ));
+
+        match tokio::time::timeout(LEGACY_ENDPOINT_TIMEOUT, endpoint_rx).await {
+            Ok(Ok(endpoint)) => Ok(LegacySse {
+                endpoint,
+                pending,
+                reader,
+            }),
+            _ => {
+                reader.abort();
+                Err(Error::SseConnection(
+                    "server did not announce a message endpoint".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Send a message over the legacy transport; replies arrive on the event stream.
+    async fn send_legacy(
+        &self,
+        legacy: &LegacySse,
+        message: JsonRpcMessage,
+    ) -> Result<JsonRpcMessage, Error> {
+        let id = match &message {
+            JsonRpcMessage::Request(request) => request.id.map(|id| id.to_string()),
+            _ => None,
+        };
+        let reply = match &id {
+            Some(id) => {
+                let (tx, rx) = oneshot::channel();
+                legacy.pending.insert(id.clone(), tx).await;
+                Some(rx)
+            }
+            None => None,
+        };
+
+        let request = self
+            .inner
+            .client
+            .post(&legacy.endpoint)
+            .headers(self.inner.headers.clone())
+            .json(&message);
+        let request = authorize(request, self.inner.auth.as_deref()).await?;
+        let response = request.send().await.map_err(http_error)?;
+        if !response.status().is_success() {
+            let error = Error::HttpError {
+                status: response.status().as_u16(),
+                message: response.status().to_string(),
+            };
+            if let Some(id) = &id {
+                // Drop the waiting entry; the reply will never come
+                legacy.pending.respond(id, Err(Error::ChannelClosed)).await;
+            }
+            return Err(error);
+        }
+
+        match reply {
+            Some(rx) => rx.await.map_err(|_| Error::ChannelClosed)?,
+            None => Ok(JsonRpcMessage::Nil),
+        }
+    }
+}
+
+/// A legacy HTTP+SSE connection, opened after falling back.
+struct LegacySse {
+    /// Where messages are POSTed, announced by the server's `endpoint` event
+    endpoint: String,
+    /// Requests waiting for their reply on the event stream
+    pending: Arc<PendingRequests>,
+    /// Reads the event stream
+    reader: JoinHandle<()>,
+}
+
+impl Drop for LegacySse {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+/// Read the legacy event stream, announcing the endpoint and routing replies.
+async fn read_legacy_stream(
+    response: Response,
+    base: url::Url,
+    pending: Arc<PendingRequests>,
+    notifications: Option<NotificationSink>,
+    endpoint_tx: oneshot::Sender<String>,
+) {
+    let mut endpoint_tx = Some(endpoint_tx);
+    let mut parser = SseParser::default();
+    let mut body = response.bytes_stream();
+    while let Some(Ok(chunk)) = body.next().await {
+        for event in parser.feed(&chunk) {
+            match event.event.as_str() {
+                "endpoint" => match base.join(&event.data) {
+                    Ok(endpoint) => {
+                        tracing::debug!(%endpoint, "discovered legacy SSE message endpoint");
+                        if let Some(tx) = endpoint_tx.take() {
+                            let _ = tx.send(endpoint.to_string());
+                        }
+                    }
+                    Err(e) => tracing::warn!("invalid legacy SSE endpoint '{}': {}", event.data, e),
+                },
+                "message" => match serde_json::from_str::<JsonRpcMessage>(&event.data) {
+                    Ok(JsonRpcMessage::Notification(notification)) => {
+                        deliver(notifications.as_ref(), notification)
+                    }
+                    Ok(message) => match response_id(&message) {
+                        Some(id) => pending.respond(&id.to_string(), Ok(message)).await,
+                        None => tracing::debug!(message = ?message, "ignoring server message"),
+                    },
+                    Err(e) => tracing::warn!("failed to parse SSE message: {}", e),
+                },
+                _ => {}
+            }
+        }
+    }
+    tracing::debug!("legacy SSE stream ended");
+    pending.clear().await;
+}
+
+#[async_trait::async_trait]
+impl TransportHandle for StreamableHttpHandle {
+    async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, Error> {
+        if let Some(legacy) = self.inner.legacy.get() {
+            return self.send_legacy(legacy, message).await;
+        }
+
+        let is_initialize =
+            matches!(&message, JsonRpcMessage::Request(r) if r.method == "initialize");
+        if is_initialize {
+            *self.inner.handshake.write().unwrap() = Some(message.clone());
+        }
+        let had_session = self.inner.session.id().is_some();
+
+        let result = match (self.exchange(&message).await, &self.inner.auth) {
+            // The token was revoked or expired early; get a new one and retry once
+            (Err(Error::HttpError { status: 401, .. }), Some(auth)) => {
+                auth.invalidate().await;
+                self.exchange(&message).await
+            }
+            (result, _) => result,
+        };
+
+        match result {
+            // A resumed or expired session; start a new one and retry once
+            Err(Error::HttpError { status: 404, .. }) if had_session => {
+                self.inner.session.set(None);
+                if !is_initialize {
+                    self.reinitialize().await?;
+                }
+                self.exchange(&message).await
+            }
+            Err(Error::HttpError { status, .. })
+                if is_initialize
+                    && self.inner.sse_fallback
+                    && matches!(status, 400 | 404 | 405) =>
+            {
+                let legacy = self.fall_back().await?;
+                self.send_legacy(legacy, message).await
+            }
+            result => result,
+        }
+    }
+}
+
+/// Add the `Authorization` header from `auth`, if any.
+async fn authorize(
+    request: RequestBuilder,
+    auth: Option<&dyn AuthProvider>,
+) -> Result<RequestBuilder, Error> {
+    let Some(auth) = auth else {
+        return Ok(request);
+    };
+    let value = auth.authorization().await.map_err(|e| Error::HttpError {
+        status: 401,
+        message: e.to_string(),
+    })?;
+    Ok(request.header(AUTHORIZATION, value))
+}
+
+fn response_id(message: &JsonRpcMessage) -> Option<u64> {
+    match message {
+        JsonRpcMessage::Response(response) => response.id,
+        JsonRpcMessage::Error(error) => error.id,
+        _ => None,
+    }
+}
+
+fn http_error(e: reqwest::Error) -> Error {
+    Error::HttpError {
+        status: e.status().map(|s| s.as_u16()).unwrap_or(0),
+        message: e.to_string(),
+    }
+}
+
+/// A server-sent event.
+#[derive(Debug, Default)]
+struct SseEvent {
+    id: Option<String>,
+    event: String,
+    data: String,
+}
+
+/// Incremental parser for `text/event-stream` bodies.
+#[derive(Default)]
+struct SseParser {
+    /// Bytes of the event being received; events may span chunks
+    buffer: Vec<u8>,
+}
+
+impl SseParser {
+    /// Add a chunk of the body and return the events it completes.
+    fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend(chunk.iter().filter(|b| **b != b'\r'));
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let block: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            let block = String::from_utf8_lossy(&block);
+            let mut event = SseEvent {
+                event: "message".to_string(),
+                ..SseEvent::default()
+            };
+            let mut data = Vec::new();
+            for line in block.lines() {
+                let (field, value) = line.split_once(':').unwrap_or((line, ""));
+                let value = value.strip_prefix(' ').unwrap_or(value);
+                match field {
+                    "id" => event.id = Some(value.to_string()),
+                    "event" => event.event = value.to_string(),
+                    "data" => data.push(value),
+                    _ => {}
+                }
+            }
+            if !data.is_empty() {
+                event.data = data.join("\n");
+                events.push(event);
+            }
+        }
+        events
+    }
+}
@@ -0,0 +1,116 @@
+// src/transport/lines.rs
+
+//! Newline-delimited JSON-RPC framing shared by the stream transports.
+//!
+//! Each message is written as a single line of JSON. Incoming lines are
+//! parsed and responses are routed to the waiting requests by ID;
+//! notifications go to the transport's sink, if any, and other server
+//! messages are logged and dropped.
+
+use crate::notifications::{deliver, NotificationSink};
+use mcp_client::transport::{Error, PendingRequests, TransportMessage};
+use mcp_spec::protocol::JsonRpcMessage;
+use std::sync::Arc;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    sync::mpsc,
+};
+
+/// Move messages between the handles and a byte stream.
+///
+/// Returns when the stream ends, a write fails, or every handle is dropped.
+/// Requests still waiting for a response are left in `pending`; callers
+/// clear it once they have recorded why the connection ended.
+pub(crate) async fn pump<R, W>(
+    reader: R,
+    writer: W,
+    receiver: mpsc::Receiver<TransportMessage>,
+    pending: Arc<PendingRequests>,
+    notifications: Option<NotificationSink>,
+) where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    tokio::select! {
+        _ = read_messages(reader, &pending, notifications.as_ref()) => {}
+        _ = write_messages(writer, receiver, &pending) => {}
+    }
+}
+
+async fn read_messages<R: AsyncRead + Unpin>(
+    reader: R,
+    pending: &PendingRequests,
+    notifications: Option<&NotificationSink>,
+) {
+    let mut lines = BufReader::new(reader).lines();
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => {
+                tracing::debug!("MCP server closed the stream");
+                return;
+            }
+            Err(e) => {
+                tracing::error!(error = ?e, "error reading from MCP server");
+                return;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<JsonRpcMessage>(&line) {
+            Ok(message) => match message {
+                JsonRpcMessage::Response(ref response) => {
+                    if let Some(id) = response.id {
+                        pending.respond(&id.to_string(), Ok(message)).await;
+                    }
+                }
+                JsonRpcMessage::Error(ref error) => {
+                    if let Some(id) = error.id {
+                        pending.respond(&id.to_string(), Ok(message)).await;
+                    }
+                }
+                JsonRpcMessage::Notification(notification) => deliver(notifications, notification),
+                other => tracing::debug!(message = ?other, "ignoring server message"),
+            },
+            Err(e) => tracing::warn!("failed to parse message from MCP server: {}", e),
+        }
+    }
+}
+
+async fn write_messages<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    mut receiver: mpsc::Receiver<TransportMessage>,
+    pending: &PendingRequests,
+) {
+    while let Some(mut message) = receiver.recv().await {
+        let mut line = match serde_json::to_string(&message.message) {
+            Ok(line) => line,
+            Err(e) => {
+                if let Some(tx) = message.response_tx.take() {
+                    let _ = tx.send(Err(Error::Serialization(e)));
+                }
+                continue;
+            }
+        };
+        line.push('\n');
+
+        if let Some(response_tx) = message.response_tx.take() {
+            if let JsonRpcMessage::Request(request) = &message.message {
+                if let Some(id) = request.id {
+                    pending.insert(id.to_string(), response_tx).await;
+                }
+            }
+        }
+
+        let written = match writer.write_all(line.as_bytes()).await {
+            Ok(()) => writer.flush().await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            tracing::error!(error = ?e, "error writing to MCP server");
+            return;
+        }
+    }
+}
@@ -0,0 +1,23 @@
+// src/transport/mod.rs
+
+//! Additional transports for MCP clients.
+//!
+//! `mcp-client` ships stdio and legacy HTTP+SSE transports. The transports in
+//! this module implement the same `Transport` and `TransportHandle` traits,
+//! so they plug into `McpConnectionManager::add_client` and the rest of the
+//! crate like the built-in ones.
+
+mod channel;
+mod http;
+mod lines;
+mod process;
+mod socket;
+mod websocket;
+
+pub use channel::{ChannelHandle, ChannelTransport, ServerChannel};
+pub use http::{HttpSession, StreamableHttpHandle, StreamableHttpTransport};
+pub use process::{ProcessHandle, ProcessTransport, ResourceLimits};
+#[cfg(unix)]
+pub use socket::UnixSocketTransport;
+pub use socket::{SocketHandle, TcpTransport};
+pub use websocket::{WebSocketHandle, WebSocketTransport};
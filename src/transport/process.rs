@@ -0,0 +1,416 @@
+// src/transport/process.rs
+
+//! Stdio transport with control over the server process.
+//!
+//! `ProcessTransport` spawns an MCP server and talks newline-delimited
+//! JSON-RPC over its stdin and stdout, like `mcp_client`'s `StdioTransport`,
+//! but also lets the host decide how the process runs:
+//!
+//! - the working directory, and whether the parent environment is inherited
+//! - stderr is forwarded line by line to `tracing`, tagged with the client ID
+//! - CPU time, address space and open file limits, applied with `setrlimit`
+//!   in the child before it starts (Unix only)
+//! - the server runs in its own process group, and the whole group is killed
+//!   when the transport's last handle is dropped or the server exits, so
+//!   helpers spawned by `npx` or `uv` do not outlive it
+
+use super::lines::pump;
+use crate::notifications::NotificationSink;
+use mcp_client::transport::{
+    send_message, Error, PendingRequests, Transport, TransportHandle, TransportMessage,
+};
+use mcp_spec::protocol::JsonRpcMessage;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    process::Stdio,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::{Child, ChildStderr, Command},
+    sync::mpsc,
+};
+
+/// How many stderr lines are kept to explain an unexpected exit.
+const STDERR_TAIL_LINES: usize = 20;
+
+/// How long to wait for stderr to close after the process exits.
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Resource limits applied to a server process.
+///
+/// Limits are only enforced on Unix; elsewhere they are ignored with a
+/// warning. Runtimes that reserve large virtual address ranges up front,
+/// such as Node, need a generous `memory_bytes`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceLimits {
+    /// CPU time in seconds (`RLIMIT_CPU`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_seconds: Option<u64>,
+    /// Address space in bytes (`RLIMIT_AS`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_bytes: Option<u64>,
+    /// Number of open file descriptors (`RLIMIT_NOFILE`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_files: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Check whether no limit is set
+    pub fn is_empty(&self) -> bool {
+        self.cpu_seconds.is_none() && self.memory_bytes.is_none() && self.open_files.is_none()
+    }
+}
+
+/// Transport that runs an MCP server as a child process.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let transport = ProcessTransport::new("npx", vec!["-y".into(), "@modelcontextprotocol/server-filesystem".into()])
+///     .with_cwd("/srv/data")
+///     .with_inherit_env(false)
+///     .with_env("PATH", "/usr/bin:/bin")
+///     .with_limits(ResourceLimits {
+///         memory_bytes: Some(4 << 30),
+///         open_files: Some(256),
+///         ..ResourceLimits::default()
+///     });
+/// manager.add_process_client("filesystem".to_string(), transport, client_info).await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProcessTransport {
+    /// Program to run
+    program: String,
+    /// Arguments passed to `program`
+    args: Vec<String>,
+    /// Environment variables set for the process
+    env: HashMap<String, String>,
+    /// Working directory, or the parent's when `None`
+    cwd: Option<PathBuf>,
+    /// Whether the process starts with the parent's environment
+    inherit_env: bool,
+    /// Client ID attached to forwarded stderr lines
+    client_id: Option<String>,
+    /// Resource limits for the process
+    limits: ResourceLimits,
+    /// Where the server's notifications go
+    notifications: Option<NotificationSink>,
+}
+
+impl ProcessTransport {
+    /// Create a transport that runs `program` with `args`.
+    pub fn new(program: impl Into<String>, args: Vec<String>) -> Self {
+        Self {
+            program: program.into(),
+            args,
+            env: HashMap::new(),
+            cwd: None,
+            inherit_env: true,
+            client_id: None,
+            limits: ResourceLimits::default(),
+            notifications: None,
+        }
+    }
+
+    /// Set an environment variable for the process.
+    pub fn with_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set several environment variables for the process.
+    pub fn with_envs(mut self, env: HashMap<String, String>) -> Self {
+        self.env.extend(env);
+        self
+    }
+
+    /// Set the working directory of the process.
+    pub fn with_cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Choose whether the process inherits the parent environment.
+    ///
+    /// When disabled, only the variables set with `with_env` are passed, so
+    /// include `PATH` if the server needs to find other programs.
+    pub fn with_inherit_env(mut self, inherit: bool) -> Self {
+        self.inherit_env = inherit;
+        self
+    }
+
+    /// Set the client ID attached to the server's stderr output.
+    pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
+    /// Set resource limits for the process.
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Deliver the server's notifications to `sink`.
+    pub fn with_notifications(mut self, sink: NotificationSink) -> Self {
+        self.notifications = Some(sink);
+        self
+    }
+
+    /// Get the client ID attached to stderr output, if set
+    pub fn client_id(&self) -> Option<&str> {
+        self.client_id.as_deref()
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if !self.inherit_env {
+            command.env_clear();
+        }
+        command.envs(&self.env);
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+
+        #[cfg(unix)]
+        {
+            // Lead a new process group so the whole tree can be killed together
+            command.process_group(0);
+            if !self.limits.is_empty() {
+                let limits = self.limits;
+                // SAFETY: the closure runs between fork and exec and only
+                // calls setrlimit, which is async-signal-safe.
+                unsafe {
+                    command.pre_exec(move || apply_limits(&limits));
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        if !self.limits.is_empty() {
+            tracing::warn!(
+                program = %self.program,
+                "resource limits are only supported on Unix and will be ignored"
+            );
+        }
+
+        command
+    }
+}
+
+#[cfg(unix)]
+fn apply_limits(limits: &ResourceLimits) -> std::io::Result<()> {
+    let set = |resource, value: u64| {
+        let limit = libc::rlimit {
+            rlim_cur: value as libc::rlim_t,
+            rlim_max: value as libc::rlim_t,
+        };
+        // SAFETY: `limit` is a valid rlimit for the duration of the call
+        if unsafe { libc::setrlimit(resource, &limit) } == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    };
+    if let Some(seconds) = limits.cpu_seconds {
+        set(libc::RLIMIT_CPU, seconds)?;
+    }
+    if let Some(bytes) = limits.memory_bytes {
+        set(libc::RLIMIT_AS, bytes)?;
+    }
+    if let Some(files) = limits.open_files {
+        set(libc::RLIMIT_NOFILE, files)?;
+    }
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl Transport for ProcessTransport {
+    type Handle = ProcessHandle;
+
+    async fn start(&self) -> Result<Self::Handle, Error> {
+        let mut child = self
+            .command()
+            .spawn()
+            .map_err(|e| Error::StdioProcessError(format!("{}: {}", self.program, e)))?;
+        let missing = |stream: &str| Error::StdioProcessError(format!("failed to get {}", stream));
+        let stdin = child.stdin.take().ok_or_else(|| missing("stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| missing("stdout"))?;
+        let stderr = child.stderr.take().ok_or_else(|| missing("stderr"))?;
+
+        let client_id = self
+            .client_id
+            .clone()
+            .unwrap_or_else(|| self.program.clone());
+        let tail = Arc::new(Mutex::new(VecDeque::new()));
+        let stderr_task =
+            tokio::spawn(forward_stderr(stderr, client_id.clone(), Arc::clone(&tail)));
+
+        let (sender, receiver) = mpsc::channel(32);
+        let pending = Arc::new(PendingRequests::new());
+        let exit = Arc::new(OnceLock::new());
+        let mut guard = ProcessGuard::new(child);
+
+        let reported = Arc::clone(&exit);
+        let notifications = self.notifications.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = pump(stdout, stdin, receiver, Arc::clone(&pending), notifications) => {}
+                status = guard.wait() => {
+                    // Let stderr drain so the tail includes the last words
+                    let _ = tokio::time::timeout(STDERR_DRAIN_TIMEOUT, stderr_task).await;
+                    let tail: Vec<String> = tail.lock().unwrap().iter().cloned().collect();
+                    let status = match status {
+                        Ok(status) => status.to_string(),
+                        Err(e) => e.to_string(),
+                    };
+                    tracing::warn!(client_id = %client_id, status = %status, "MCP server process exited");
+                    let _ = reported.set(format!("process exited with {}: {}", status, tail.join("\n")));
+                }
+            }
+            pending.clear().await;
+            drop(guard);
+        });
+
+        Ok(ProcessHandle { sender, exit })
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Handle for sending messages to a server process.
+#[derive(Clone)]
+pub struct ProcessHandle {
+    sender: mpsc::Sender<TransportMessage>,
+    /// Why the process exited, once it has
+    exit: Arc<OnceLock<String>>,
+}
+
+#[async_trait::async_trait]
+impl TransportHandle for ProcessHandle {
+    async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, Error> {
+        match send_message(&self.sender, message).await {
+            Err(Error::ChannelClosed) => match self.exit.get() {
+                Some(reason) => Err(Error::StdioProcessError(reason.clone())),
+                None => Err(Error::ChannelClosed),
+            },
+            result => result,
+        }
+    }
+}
+
+/// Forward stderr lines to `tracing` and keep the last few.
+async fn forward_stderr(
+    stderr: ChildStderr,
+    client_id: String,
+    tail: Arc<Mutex<VecDeque<String>>>,
+) {
+    let mut lines = BufReader::new(stderr).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        tracing::info!(target: "mcp_rig::stderr", client_id = %client_id, "{}", line);
+        let mut tail = tail.lock().unwrap();
+        if tail.len() == STDERR_TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+}
+
+/// Kills the server's process group when dropped or when the server exits.
+///
+/// Once the server has been reaped its ID may be reused, so the group is
+/// only signalled before that.
+struct ProcessGuard {
+    child: Child,
+    /// The process group, which has the ID of the server process
+    #[cfg(unix)]
+    group: Option<i32>,
+    /// Whether the server has exited and been, or is about to be, reaped
+    #[cfg(unix)]
+    reaped: bool,
+}
+
+impl ProcessGuard {
+    fn new(child: Child) -> Self {
+        Self {
+            #[cfg(unix)]
+            group: child.id().map(|id| id as i32),
+            child,
+            #[cfg(unix)]
+            reaped: false,
+        }
+    }
+
+    /// Wait for the server to exit, then kill its group and reap it.
+    ///
+    /// The exited server is left unreaped until the group has been killed,
+    /// so its ID cannot have been reused by an unrelated process.
+    async fn wait(&mut self) -> std::io::Result<std::process::ExitStatus> {
+        #[cfg(unix)]
+        if let Some(group) = self.group {
+            let exited = tokio::task::spawn_blocking(move || wait_without_reaping(group)).await;
+            if let Ok(Err(e)) = exited {
+                tracing::debug!(error = %e, "failed to wait for the server process");
+            }
+            self.kill_group();
+            self.reaped = true;
+        }
+        self.child.wait().await
+    }
+
+    #[cfg(unix)]
+    fn kill_group(&self) {
+        if let Some(group) = self.group {
+            // SAFETY: killpg has no memory safety requirements
+            unsafe {
+                libc::killpg(group, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+impl Drop for ProcessGuard {
+    fn drop(&mut self) {
+        // kill_on_drop takes care of the server itself; this reaches anything it spawned
+        #[cfg(unix)]
+        if !self.reaped {
+            self.kill_group();
+        }
+    }
+}
+
+/// Block until the process exits, leaving it to be reaped by `Child::wait`.
+#[cfg(unix)]
+fn wait_without_reaping(pid: i32) -> std::io::Result<()> {
+    loop {
+        // SAFETY: siginfo_t is plain data, and waitid only writes to it
+        let result = unsafe {
+            let mut info: libc::siginfo_t = std::mem::zeroed();
+            libc::waitid(
+                libc::P_PID,
+                pid as libc::id_t,
+                &mut info,
+                libc::WEXITED | libc::WNOWAIT,
+            )
+        };
+        if result == 0 {
+            return Ok(());
+        }
+        let error = std::io::Error::last_os_error();
+        if error.kind() != std::io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+// src/transport/socket.rs
+
+//! Unix domain socket and TCP transports.
+//!
+//! Both speak newline-delimited JSON-RPC, the same framing as stdio, so a
+//! server running as a long-lived local daemon can be shared by several
+//! agent processes instead of each spawning its own copy. Every transport
+//! opens its own connection; the server sees one session per connection.
+
+use super::lines::pump;
+use crate::notifications::NotificationSink;
+use mcp_client::transport::{
+    send_message, Error, PendingRequests, Transport, TransportHandle, TransportMessage,
+};
+use mcp_spec::protocol::JsonRpcMessage;
+use std::sync::Arc;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    sync::mpsc,
+};
+
+#[cfg(unix)]
+use std::path::PathBuf;
+
+/// Transport for a server listening on a Unix domain socket.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let transport = UnixSocketTransport::new("/run/mcp/git.sock");
+/// manager.add_client("git".to_string(), transport, client_info).await?;
+/// ```
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub struct UnixSocketTransport {
+    /// Path of the socket
+    path: PathBuf,
+    /// Where the server's notifications go
+    notifications: Option<NotificationSink>,
+}
+
+#[cfg(unix)]
+impl UnixSocketTransport {
+    /// Create a transport for the socket at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            notifications: None,
+        }
+    }
+
+    /// Deliver the server's notifications to `sink`.
+    pub fn with_notifications(mut self, sink: NotificationSink) -> Self {
+        self.notifications = Some(sink);
+        self
+    }
+}
+
+#[cfg(unix)]
+#[async_trait::async_trait]
+impl Transport for UnixSocketTransport {
+    type Handle = SocketHandle;
+
+    async fn start(&self) -> Result<Self::Handle, Error> {
+        let stream = tokio::net::UnixStream::connect(&self.path).await?;
+        let (reader, writer) = stream.into_split();
+        Ok(SocketHandle::spawn(
+            reader,
+            writer,
+            self.notifications.clone(),
+        ))
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Transport for a server listening on a TCP port.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let transport = TcpTransport::new("127.0.0.1:9400");
+/// manager.add_client("search".to_string(), transport, client_info).await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct TcpTransport {
+    /// `host:port` of the server
+    address: String,
+    /// Where the server's notifications go
+    notifications: Option<NotificationSink>,
+}
+
+impl TcpTransport {
+    /// Create a transport for the server at `address`.
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            notifications: None,
+        }
+    }
+
+    /// Deliver the server's notifications to `sink`.
+    pub fn with_notifications(mut self, sink: NotificationSink) -> Self {
+        self.notifications = Some(sink);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for TcpTransport {
+    type Handle = SocketHandle;
+
+    async fn start(&self) -> Result<Self::Handle, Error> {
+        let stream = TcpStream::connect(&self.address).await?;
+        // Messages are small and latency matters more than throughput
+        stream.set_nodelay(true)?;
+        let (reader, writer) = stream.into_split();
+        Ok(SocketHandle::spawn(
+            reader,
+            writer,
+            self.notifications.clone(),
+        ))
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Handle for sending messages over a socket.
+///
+/// The connection is closed when every handle is dropped.
+#[derive(Clone)]
+pub struct SocketHandle {
+    sender: mpsc::Sender<TransportMessage>,
+}
+
+impl SocketHandle {
+    fn spawn<R, W>(reader: R, writer: W, notifications: Option<NotificationSink>) -> Self
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(32);
+        let pending = Arc::new(PendingRequests::new());
+        tokio::spawn(async move {
+            pump(
+                reader,
+                writer,
+                receiver,
+                Arc::clone(&pending),
+                notifications,
+            )
+            .await;
+            pending.clear().await;
+        });
+        Self { sender }
+    }
+}
+
+#[async_trait::async_trait]
+impl TransportHandle for SocketHandle {
+    async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, Error> {
+        send_message(&self.sender, message).await
+    }
+}
@@ -0,0 +1,496 @@
+// src/transport/websocket.rs
+
+//! WebSocket transport.
+//!
+//! Every JSON-RPC message travels in its own text frame; binary frames are
+//! accepted as UTF-8 text and arrays are treated as batches. A background
+//! actor owns the socket and routes responses to the waiting requests by ID.
+//!
+//! The actor keeps the connection healthy:
+//!
+//! - it sends a WebSocket ping every `ping_interval` and treats a missing
+//!   pong by the next ping as a dead connection
+//! - it answers JSON-RPC `ping` requests from the server
+//! - when the connection is lost it fails the requests in flight, then
+//!   reconnects with exponential backoff and replays the `initialize`
+//!   handshake before sending anything else
+//!
+//! Messages sent while reconnecting wait until the connection is back.
+//!
+//! With an `AuthProvider`, the `Authorization` header is fetched for every
+//! connection attempt, so reconnects use a fresh token. An upgrade rejected
+//! with 401 invalidates the token and is retried once.
+
+use crate::auth::AuthProvider;
+use crate::notifications::{deliver, NotificationSink};
+use futures::{SinkExt, StreamExt};
+use mcp_client::transport::{
+    send_message, Error, PendingRequests, Transport, TransportHandle, TransportMessage,
+};
+use mcp_spec::protocol::{JsonRpcMessage, JsonRpcNotification};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::{
+    net::TcpStream,
+    sync::{mpsc, watch},
+};
+use tokio_tungstenite::{
+    tungstenite::{
+        self,
+        client::IntoClientRequest,
+        http::{header::AUTHORIZATION, HeaderName, HeaderValue},
+        Bytes, Message,
+    },
+    MaybeTlsStream, WebSocketStream,
+};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Upper bound for the delay between reconnection attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Transport for servers reachable over a WebSocket.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let transport = WebSocketTransport::new("wss://gateway.example.com/mcp")
+///     .with_header("Authorization", "Bearer secret")
+///     .with_ping_interval(Duration::from_secs(15));
+/// manager.add_client("remote".to_string(), transport, client_info).await?;
+/// ```
+#[derive(Clone)]
+pub struct WebSocketTransport {
+    /// The `ws://` or `wss://` endpoint
+    url: String,
+    /// Extra headers sent with the upgrade request
+    headers: HashMap<String, String>,
+    /// How often to ping the server
+    ping_interval: Duration,
+    /// How many times to try to reconnect before giving up
+    max_reconnects: u32,
+    /// Delay before the first reconnection attempt, doubled after each failure
+    reconnect_delay: Duration,
+    /// Supplies the `Authorization` header
+    auth: Option<Arc<dyn AuthProvider>>,
+    /// Where the server's notifications go
+    notifications: Option<NotificationSink>,
+    /// Tells the actor to close the connection
+    shutdown: Arc<watch::Sender<bool>>,
+}
+
+impl WebSocketTransport {
+    /// Create a transport for the endpoint at `url`.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            headers: HashMap::new(),
+            ping_interval: Duration::from_secs(30),
+            max_reconnects: 5,
+            reconnect_delay: Duration::from_millis(500),
+            auth: None,
+            notifications: None,
+            shutdown: Arc::new(watch::Sender::new(false)),
+        }
+    }
+
+    /// Send a header with the upgrade request.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Send several headers with the upgrade request.
+    pub fn with_headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Authorize every connection attempt with a header from `provider`.
+    pub fn with_auth(mut self, provider: Arc<dyn AuthProvider>) -> Self {
+        self.auth = Some(provider);
+        self
+    }
+
+    /// Deliver the server's notifications to `sink`.
+    pub fn with_notifications(mut self, sink: NotificationSink) -> Self {
+        self.notifications = Some(sink);
+        self
+    }
+
+    /// Set how often the server is pinged to detect dead connections.
+    pub fn with_ping_interval(mut self, interval: Duration) -> Self {
+        self.ping_interval = interval;
+        self
+    }
+
+    /// Set how many reconnection attempts are made and the initial delay between them.
+    ///
+    /// Use zero attempts to disable reconnecting.
+    pub fn with_reconnect(mut self, max_attempts: u32, initial_delay: Duration) -> Self {
+        self.max_reconnects = max_attempts;
+        self.reconnect_delay = initial_delay;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for WebSocketTransport {
+    type Handle = WebSocketHandle;
+
+    async fn start(&self) -> Result<Self::Handle, Error> {
+        let socket = connect(&self.url, &self.headers, self.auth.as_deref()).await?;
+
+        let (sender, receiver) = mpsc::channel(32);
+        let handshake = Arc::new(RwLock::new(None));
+        let actor = WebSocketActor {
+            url: self.url.clone(),
+            headers: self.headers.clone(),
+            auth: self.auth.clone(),
+            notifications: self.notifications.clone(),
+            receiver,
+            pending: Arc::new(PendingRequests::new()),
+            handshake: Arc::clone(&handshake),
+            ping_interval: self.ping_interval,
+            max_reconnects: self.max_reconnects,
+            reconnect_delay: self.reconnect_delay,
+            shutdown: self.shutdown.subscribe(),
+        };
+        tokio::spawn(actor.run(socket));
+
+        Ok(WebSocketHandle { sender, handshake })
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        self.shutdown.send_replace(true);
+        Ok(())
+    }
+}
+
+/// Handle for sending messages over a WebSocket.
+#[derive(Clone)]
+pub struct WebSocketHandle {
+    sender: mpsc::Sender<TransportMessage>,
+    /// The `initialize` request, replayed after reconnecting
+    handshake: Arc<RwLock<Option<JsonRpcMessage>>>,
+}
+
+#[async_trait::async_trait]
+impl TransportHandle for WebSocketHandle {
+    async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, Error> {
+        if matches!(&message, JsonRpcMessage::Request(r) if r.method == "initialize") {
+            *self.handshake.write().unwrap() = Some(message.clone());
+        }
+        send_message(&self.sender, message).await
+    }
+}
+
+/// Why the actor stopped serving a connection.
+enum Disconnect {
+    /// All handles were dropped or the transport was closed
+    Stop,
+    /// The connection failed
+    Lost(String),
+}
+
+/// Owns the socket and moves messages between it and the handles.
+struct WebSocketActor {
+    url: String,
+    headers: HashMap<String, String>,
+    auth: Option<Arc<dyn AuthProvider>>,
+    notifications: Option<NotificationSink>,
+    receiver: mpsc::Receiver<TransportMessage>,
+    pending: Arc<PendingRequests>,
+    handshake: Arc<RwLock<Option<JsonRpcMessage>>>,
+    ping_interval: Duration,
+    max_reconnects: u32,
+    reconnect_delay: Duration,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl WebSocketActor {
+    async fn run(mut self, mut socket: Socket) {
+        loop {
+            match self.serve(&mut socket).await {
+                Disconnect::Stop => {
+                    let _ = socket.close(None).await;
+                    break;
+                }
+                Disconnect::Lost(reason) => {
+                    tracing::warn!(url = %self.url, reason = %reason, "WebSocket connection lost");
+                    // The server will never answer requests sent on the old connection
+                    self.pending.clear().await;
+                    match self.reconnect().await {
+                        Some(reconnected) => socket = reconnected,
+                        None => break,
+                    }
+                }
+            }
+        }
+        self.pending.clear().await;
+    }
+
+    async fn serve(&mut self, socket: &mut Socket) -> Disconnect {
+        let mut ping = tokio::time::interval(self.ping_interval);
+        ping.tick().await;
+        let mut awaiting_pong = false;
+
+        loop {
+            tokio::select! {
+                Ok(()) = self.shutdown.changed() => return Disconnect::Stop,
+                outgoing = self.receiver.recv() => {
+                    let Some(message) = outgoing else {
+                        return Disconnect::Stop;
+                    };
+                    if let Err(e) = write(socket, &self.pending, message).await {
+                        return Disconnect::Lost(e);
+                    }
+                }
+                incoming = socket.next() => {
+                    let text = match incoming {
+                        Some(Ok(Message::Text(text))) => text.to_string(),
+                        Some(Ok(Message::Binary(bytes))) => String::from_utf8_lossy(&bytes).into_owned(),
+                        Some(Ok(Message::Pong(_))) => {
+                            awaiting_pong = false;
+                            continue;
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            return Disconnect::Lost(format!("server closed the connection: {:?}", frame));
+                        }
+                        // Pings are answered by tungstenite
+                        Some(Ok(_)) => continue,
+                        Some(Err(e)) => return Disconnect::Lost(e.to_string()),
+                        None => return Disconnect::Lost("connection closed".to_string()),
+                    };
+                    for reply in route(&self.pending, self.notifications.as_ref(), &text).await {
+                        if let Err(e) = socket.send(Message::text(reply.to_string())).await {
+                            return Disconnect::Lost(e.to_string());
+                        }
+                    }
+                }
+                _ = ping.tick() => {
+                    if awaiting_pong {
+                        return Disconnect::Lost("server did not answer ping".to_string());
+                    }
+                    awaiting_pong = true;
+                    if let Err(e) = socket.send(Message::Ping(Bytes::new())).await {
+                        return Disconnect::Lost(e.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reconnect with exponential backoff and replay the handshake.
+    async fn reconnect(&mut self) -> Option<Socket> {
+        let mut delay = self.reconnect_delay;
+        for attempt in 1..=self.max_reconnects {
+            tokio::select! {
+                Ok(()) = self.shutdown.changed() => return None,
+                _ = tokio::time::sleep(delay) => {}
+            }
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+
+            let result = match connect(&self.url, &self.headers, self.auth.as_deref()).await {
+                Ok(mut socket) => self.replay_handshake(&mut socket).await.map(|_| socket),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(socket) => {
+                    tracing::info!(url = %self.url, attempt, "WebSocket reconnected");
+                    return Some(socket);
+                }
+                Err(e) => {
+                    tracing::warn!(url = %self.url, attempt, error = %e, "WebSocket reconnect failed")
+                }
+            }
+        }
+        tracing::error!(url = %self.url, "giving up on WebSocket connection");
+        None
+    }
+
+    /// Start a new MCP session on a fresh connection.
+    async fn replay_handshake(&self, socket: &mut Socket) -> Result<(), String> {
+        let handshake = self.handshake.read().unwrap().clone();
+        let Some(JsonRpcMessage::Request(initialize)) = handshake else {
+            // The client never initialized, so there is nothing to restore
+            return Ok(());
+        };
+
+        let text = serde_json::to_string(&initialize).map_err(|e| e.to_string())?;
+        socket
+            .send(Message::text(text))
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // Wait for the reply before announcing the client is initialized
+        loop {
+            let text = match socket.next().await {
+                Some(Ok(Message::Text(text))) => text.to_string(),
+                Some(Ok(Message::Binary(bytes))) => String::from_utf8_lossy(&bytes).into_owned(),
+                Some(Ok(Message::Close(_))) | None => {
+                    return Err("connection closed during handshake".to_string())
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(e.to_string()),
+            };
+            let replied = messages(&text).iter().any(|m| match m {
+                JsonRpcMessage::Response(r) => r.id == initialize.id,
+                JsonRpcMessage::Error(e) => e.id == initialize.id,
+                _ => false,
+            });
+            if replied {
+                break;
+            }
+        }
+
+        let initialized = JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: "notifications/initialized".to_string(),
+            params: None,
+        });
+        let text = serde_json::to_string(&initialized).map_err(|e| e.to_string())?;
+        socket
+            .send(Message::text(text))
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Open a WebSocket connection with the given upgrade headers.
+async fn connect(
+    url: &str,
+    headers: &HashMap<String, String>,
+    auth: Option<&dyn AuthProvider>,
+) -> Result<Socket, Error> {
+    match (open(url, headers, auth).await, auth) {
+        // The token was revoked or expired early; get a new one and retry once
+        (Err(Error::HttpError { status: 401, .. }), Some(auth)) => {
+            auth.invalidate().await;
+            open(url, headers, Some(auth)).await
+        }
+        (result, _) => result,
+    }
+}
+
+async fn open(
+    url: &str,
+    headers: &HashMap<String, String>,
+    auth: Option<&dyn AuthProvider>,
+) -> Result<Socket, Error> {
+    let mut request = url.into_client_request().map_err(ws_error)?;
+    for (name, value) in headers {
+        let invalid = |e: &dyn std::fmt::Display| Error::HttpError {
+            status: 0,
+            message: format!("invalid header '{}': {}", name, e),
+        };
+        let header = HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(&e))?;
+        let value = HeaderValue::from_str(value).map_err(|e| invalid(&e))?;
+        request.headers_mut().insert(header, value);
+    }
+    if let Some(auth) = auth {
+        let value = auth.authorization().await.map_err(|e| Error::HttpError {
+            status: 401,
+            message: e.to_string(),
+        })?;
+        let value = HeaderValue::from_str(&value).map_err(|e| Error::HttpError {
+            status: 0,
+            message: format!("invalid authorization header: {}", e),
+        })?;
+        request.headers_mut().insert(AUTHORIZATION, value);
+    }
+    let (socket, _) = tokio_tungstenite::connect_async(request)
+        .await
+        .map_err(ws_error)?;
+    Ok(socket)
+}
+
+/// Send a message, registering requests so their responses can be routed.
+async fn write(
+    socket: &mut Socket,
+    pending: &PendingRequests,
+    mut message: TransportMessage,
+) -> Result<(), String> {
+    let text = match serde_json::to_string(&message.message) {
+        Ok(text) => text,
+        Err(e) => {
+            if let Some(tx) = message.response_tx.take() {
+                let _ = tx.send(Err(Error::Serialization(e)));
+            }
+            return Ok(());
+        }
+    };
+
+    if let Some(response_tx) = message.response_tx.take() {
+        if let JsonRpcMessage::Request(request) = &message.message {
+            if let Some(id) = request.id {
+                pending.insert(id.to_string(), response_tx).await;
+            }
+        }
+    }
+
+    socket
+        .send(Message::text(text))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Deliver the messages in a frame and return replies to send back.
+async fn route(
+    pending: &PendingRequests,
+    notifications: Option<&NotificationSink>,
+    text: &str,
+) -> Vec<Value> {
+    let mut replies = Vec::new();
+    for message in messages(text) {
+        match message {
+            JsonRpcMessage::Response(ref response) => {
+                if let Some(id) = response.id {
+                    pending.respond(&id.to_string(), Ok(message)).await;
+                }
+            }
+            JsonRpcMessage::Error(ref error) => {
+                if let Some(id) = error.id {
+                    pending.respond(&id.to_string(), Ok(message)).await;
+                }
+            }
+            JsonRpcMessage::Request(ref request) if request.method == "ping" => {
+                replies.push(json!({ "jsonrpc": "2.0", "id": request.id, "result": {} }));
+            }
+            JsonRpcMessage::Notification(notification) => deliver(notifications, notification),
+            other => tracing::debug!(message = ?other, "ignoring server message"),
+        }
+    }
+    replies
+}
+
+/// Parse a frame holding a single message or a batch.
+fn messages(text: &str) -> Vec<JsonRpcMessage> {
+    let items = match serde_json::from_str::<Value>(text) {
+        Ok(Value::Array(items)) => items,
+        Ok(single) => vec![single],
+        Err(e) => {
+            tracing::warn!("failed to parse WebSocket message: {}", e);
+            return Vec::new();
+        }
+    };
+    items
+        .into_iter()
+        .filter_map(|item| serde_json::from_value(item).ok())
+        .collect()
+}
+
+fn ws_error(e: tungstenite::Error) -> Error {
+    match e {
+        tungstenite::Error::Http(response) => Error::HttpError {
+            status: response.status().as_u16(),
+            message: "WebSocket upgrade was rejected".to_string(),
+        },
+        tungstenite::Error::Io(e) => Error::Io(e),
+        other => Error::Io(std::io::Error::other(other)),
+    }
+}
@@ -0,0 +1,391 @@
+// src/truncation.rs
+
+//! Output size limits for tool results.
+//!
+//! A single `read_file` or `search` call can return megabytes of text, all
+//! of which would otherwise be copied into the model context. An
+//! `OutputBudget` caps the text of a result in characters or estimated
+//! tokens and truncates it with a `TruncationStrategy` when it is exceeded.
+//!
+//! With an `OutputStash`, the full text of truncated results is kept under a
+//! handle and the truncation marker tells the model how to read the rest
+//! with the `read_tool_output` tool returned by `OutputStash::reader_tool`.
+
+use crate::error::McpRigIntegrationError;
+use mcp_spec::{Content, ResourceContents};
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+/// Rough number of characters per token used to estimate token counts.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// The unit an `OutputBudget` limit is expressed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BudgetUnit {
+    /// Unicode characters
+    #[default]
+    Chars,
+    /// Estimated tokens, at four characters per token
+    Tokens,
+}
+
+/// How a result that exceeds its budget is shortened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TruncationStrategy {
+    /// Keep the beginning
+    Head,
+    /// Keep the end, which suits logs
+    Tail,
+    /// Keep the beginning and the end
+    #[default]
+    HeadTail,
+    /// Replace the whole result with a short note of its size
+    SummaryMarker,
+}
+
+/// A limit on the text returned by a tool.
+///
+/// # Example
+///
+/// ```rust
+/// use mcp_rig::{OutputBudget, TruncationStrategy};
+///
+/// let budget = OutputBudget::tokens(2_000).with_strategy(TruncationStrategy::Tail);
+/// assert_eq!(budget.max_chars(), 8_000);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputBudget {
+    /// The maximum size of the result text
+    pub limit: usize,
+    /// The unit of `limit`
+    #[serde(default)]
+    pub unit: BudgetUnit,
+    /// How oversized results are shortened
+    #[serde(default)]
+    pub strategy: TruncationStrategy,
+}
+
+impl OutputBudget {
+    /// Create a budget measured in characters.
+    pub fn chars(limit: usize) -> Self {
+        Self {
+            limit,
+            unit: BudgetUnit::Chars,
+            strategy: TruncationStrategy::default(),
+        }
+    }
+
+    /// Create a budget measured in estimated tokens.
+    pub fn tokens(limit: usize) -> Self {
+        Self {
+            limit,
+            unit: BudgetUnit::Tokens,
+            strategy: TruncationStrategy::default(),
+        }
+    }
+
+    /// Set the truncation strategy.
+    pub fn with_strategy(mut self, strategy: TruncationStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Get the limit in characters
+    pub fn max_chars(&self) -> usize {
+        match self.unit {
+            BudgetUnit::Chars => self.limit,
+            BudgetUnit::Tokens => self.limit.saturating_mul(CHARS_PER_TOKEN),
+        }
+    }
+
+    /// Shorten text that exceeds the budget.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: The full result text
+    /// - `handle`: The stash handle of the full text, mentioned in the marker
+    ///
+    /// # Returns
+    ///
+    /// The truncated text, or `None` if it fits the budget
+    pub fn truncate(&self, text: &str, handle: Option<&str>) -> Option<String> {
+        let total = text.chars().count();
+        let max = self.max_chars();
+        if total <= max {
+            return None;
+        }
+
+        let lines = text.lines().count();
+        let hint = match handle {
+            Some(handle) => format!(
+                "; call read_tool_output with handle \"{}\" to read the full output",
+                handle
+            ),
+            None => String::new(),
+        };
+
+        let truncated = match self.strategy {
+            TruncationStrategy::Head => format!(
+                "{}\n[... {} of {} characters omitted{} ...]",
+                take_chars(text, max),
+                total - max,
+                total,
+                hint
+            ),
+            TruncationStrategy::Tail => format!(
+                "[... {} of {} characters omitted{} ...]\n{}",
+                total - max,
+                total,
+                hint,
+                skip_chars(text, total - max)
+            ),
+            TruncationStrategy::HeadTail => {
+                let head = max / 2;
+                let tail = max - head;
+                format!(
+                    "{}\n[... {} of {} characters omitted{} ...]\n{}",
+                    take_chars(text, head),
+                    total - max,
+                    total,
+                    hint,
+                    skip_chars(text, total - tail)
+                )
+            }
+            TruncationStrategy::SummaryMarker => format!(
+                "[output of {} characters across {} lines omitted because it exceeds the {}-character limit{}]",
+                total, lines, max, hint
+            ),
+        };
+        Some(truncated)
+    }
+}
+
+fn take_chars(text: &str, count: usize) -> &str {
+    match text.char_indices().nth(count) {
+        Some((index, _)) => &text[..index],
+        None => text,
+    }
+}
+
+fn skip_chars(text: &str, count: usize) -> &str {
+    match text.char_indices().nth(count) {
+        Some((index, _)) => &text[index..],
+        None => "",
+    }
+}
+
+/// Apply a budget to the text items of a tool result.
+///
+/// When the combined text exceeds the budget, the text items are joined and
+/// replaced by a single truncated item; images and blobs are kept as they
+/// are. If a stash is given, the full text is stored in it first.
+pub(crate) fn apply_budget(
+    tool_name: &str,
+    budget: &OutputBudget,
+    stash: Option<&OutputStash>,
+    content: &mut Vec<Content>,
+) {
+    let texts: Vec<&str> = content.iter().filter_map(text_of).collect();
+    let total: usize = texts.iter().map(|t| t.chars().count()).sum();
+    if total <= budget.max_chars() {
+        return;
+    }
+
+    let full = texts.join("\n");
+    let handle = stash.map(|stash| stash.insert(tool_name, full.clone()));
+    let Some(truncated) = budget.truncate(&full, handle.as_deref()) else {
+        return;
+    };
+
+    tracing::info!(
+        tool = tool_name,
+        chars = total,
+        limit = budget.max_chars(),
+        strategy = ?budget.strategy,
+        handle = handle.as_deref().unwrap_or(""),
+        "truncated tool output"
+    );
+
+    content.retain(|item| text_of(item).is_none());
+    content.insert(0, Content::text(truncated));
+}
+
+fn text_of(item: &Content) -> Option<&str> {
+    match item {
+        Content::Text(text) => Some(&text.text),
+        Content::Resource(resource) => match &resource.resource {
+            ResourceContents::TextResourceContents { text, .. } => Some(text),
+            ResourceContents::BlobResourceContents { .. } => None,
+        },
+        Content::Image(_) => None,
+    }
+}
+
+#[derive(Default)]
+struct StashInner {
+    next_id: u64,
+    entries: HashMap<String, String>,
+    order: VecDeque<String>,
+}
+
+/// Keeps the full text of truncated results so the model can page through them.
+///
+/// The stash holds at most `capacity` results and drops the oldest first.
+/// Clones share the same storage.
+#[derive(Clone)]
+pub struct OutputStash {
+    inner: Arc<Mutex<StashInner>>,
+    capacity: usize,
+}
+
+impl Default for OutputStash {
+    fn default() -> Self {
+        Self::new(32)
+    }
+}
+
+impl OutputStash {
+    /// Create a stash holding at most `capacity` results.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(StashInner::default())),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Store a result and return its handle
+    pub fn insert(&self, tool_name: &str, text: String) -> String {
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_id += 1;
+        let handle = format!("{}-{}", tool_name, inner.next_id);
+        inner.entries.insert(handle.clone(), text);
+        inner.order.push_back(handle.clone());
+        while inner.order.len() > self.capacity {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.entries.remove(&oldest);
+            }
+        }
+        handle
+    }
+
+    /// Read `length` characters of a stored result starting at `offset`.
+    ///
+    /// Reading past the end returns an empty page.
+    pub fn read(&self, handle: &str, offset: usize, length: usize) -> Option<StashPage> {
+        let inner = self.inner.lock().unwrap();
+        let text = inner.entries.get(handle)?;
+        let total = text.chars().count();
+        // Both come from the model, so they may be arbitrarily large
+        let offset = offset.min(total);
+        let page = take_chars(skip_chars(text, offset), length).to_string();
+        let end = offset.saturating_add(length).min(total);
+        Some(StashPage {
+            text: page,
+            offset,
+            total,
+            next_offset: (end < total).then_some(end),
+        })
+    }
+
+    /// Create the follow-up tool that reads from this stash.
+    pub fn reader_tool(&self) -> ReadOutputTool {
+        ReadOutputTool {
+            stash: self.clone(),
+            page_size: 4_000,
+        }
+    }
+}
+
+/// A page of a stashed result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StashPage {
+    /// The requested characters
+    pub text: String,
+    /// Character offset of `text` in the full result
+    pub offset: usize,
+    /// Length of the full result in characters
+    pub total: usize,
+    /// Offset to pass to read the next page, if any
+    pub next_offset: Option<usize>,
+}
+
+/// Arguments for `ReadOutputTool`.
+#[derive(Deserialize)]
+pub struct ReadOutputArgs {
+    /// Handle from a truncation marker
+    pub handle: String,
+    /// Character offset to start reading at
+    #[serde(default)]
+    pub offset: usize,
+    /// Number of characters to read
+    #[serde(default)]
+    pub length: Option<usize>,
+}
+
+/// Tool that lets the model page through stashed results.
+///
+/// Register it alongside tools that use `McpToolAdapter::with_output_stash`.
+#[derive(Clone)]
+pub struct ReadOutputTool {
+    stash: OutputStash,
+    page_size: usize,
+}
+
+impl ReadOutputTool {
+    /// Set the default and maximum number of characters per page.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+}
+
+impl Tool for ReadOutputTool {
+    const NAME: &'static str = "read_tool_output";
+
+    type Error = McpRigIntegrationError;
+    type Args = ReadOutputArgs;
+    type Output = Value;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Read part of a tool output that was truncated. Use the handle \
+                          from the truncation notice and page through with offset."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "handle": { "type": "string", "description": "Handle from the truncation notice" },
+                    "offset": { "type": "integer", "minimum": 0, "description": "Character offset to start at" },
+                    "length": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": self.page_size,
+                        "description": "Number of characters to read"
+                    }
+                },
+                "required": ["handle"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let length = args.length.unwrap_or(self.page_size).min(self.page_size);
+        match self.stash.read(&args.handle, args.offset, length) {
+            Some(page) => Ok(serde_json::to_value(page)?),
+            None => Ok(Value::String(format!(
+                "No stored output with handle '{}'; it may have expired.",
+                args.handle
+            ))),
+        }
+    }
+}
@@ -0,0 +1,426 @@
+// src/validation.rs
+
+//! Local validation of tool arguments against the tool's JSON Schema.
+//!
+//! LLMs regularly produce arguments that don't match a tool's schema. Catching
+//! those mistakes before the request leaves the process saves a round trip
+//! and lets us hand the model a message that points at the exact failing
+//! fields. The adapter returns that message as tool output rather than an
+//! error, since Rig ends the turn on tool errors.
+//!
+//! The validator covers the subset of JSON Schema that MCP servers use in
+//! practice: `type`, `enum`, `const`, `properties`, `required`,
+//! `additionalProperties`, `items`, length and range bounds, the `anyOf`,
+//! `oneOf` and `allOf` combinators, and local `$ref`s. Unknown keywords are
+//! ignored rather than rejected.
+
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// How an adapter checks arguments before calling the tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationMode {
+    /// Forward arguments without checking them
+    Disabled,
+    /// Reject arguments that don't match the schema
+    #[default]
+    Validate,
+    /// Fix common LLM mistakes first, then reject anything still invalid
+    Coerce,
+}
+
+/// A single schema violation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// JSON Pointer to the offending value, empty for the root
+    pub path: String,
+    /// Description of what is wrong
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "(root): {}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Validate `value` against `schema`, returning every violation found.
+pub fn validate(schema: &Value, value: &Value) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    Validator { root: schema }.check(schema, value, "", &mut issues);
+    issues
+}
+
+/// Coerce common LLM mistakes in `value` to match `schema`.
+///
+/// Numbers and booleans sent as strings are parsed, scalars are wrapped in a
+/// single-element array where an array is expected, JSON objects sent as
+/// strings are decoded, and numbers are stringified where a string is
+/// expected. Returns a description of every change that was made.
+pub fn coerce(schema: &Value, value: &mut Value) -> Vec<String> {
+    let mut changes = Vec::new();
+    Validator { root: schema }.coerce(schema, value, "", &mut changes);
+    changes
+}
+
+/// Format validation issues as an error message the model can act on.
+pub fn describe_issues(tool_name: &str, issues: &[ValidationIssue]) -> String {
+    let mut message = format!("Invalid arguments for tool '{}':", tool_name);
+    for issue in issues {
+        message.push_str("\n- ");
+        message.push_str(&issue.to_string());
+    }
+    message
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn matches_type(expected: &str, value: &Value) -> bool {
+    match expected {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => match value {
+            Value::Number(n) => {
+                n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|f| f.fract() == 0.0)
+            }
+            _ => false,
+        },
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+fn declared_types(schema: &Map<String, Value>) -> Vec<&str> {
+    match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn child_path(path: &str, segment: &str) -> String {
+    let escaped = segment.replace('~', "~0").replace('/', "~1");
+    format!("{}/{}", path, escaped)
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+}
+
+impl<'a> Validator<'a> {
+    /// Resolve a local `$ref` such as `#/$defs/Item` against the root schema.
+    fn resolve(&self, schema: &'a Value) -> &'a Value {
+        let mut current = schema;
+        // Bound the number of hops so reference cycles can't loop forever
+        for _ in 0..32 {
+            let Some(reference) = current.get("$ref").and_then(Value::as_str) else {
+                return current;
+            };
+            let Some(pointer) = reference.strip_prefix('#') else {
+                return current;
+            };
+            match self.root.pointer(pointer) {
+                Some(target) => current = target,
+                None => return current,
+            }
+        }
+        current
+    }
+
+    fn check(
+        &self,
+        schema: &'a Value,
+        value: &Value,
+        path: &str,
+        issues: &mut Vec<ValidationIssue>,
+    ) {
+        let schema = self.resolve(schema);
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => {
+                issues.push(ValidationIssue {
+                    path: path.to_string(),
+                    message: "no value is allowed here".to_string(),
+                });
+                return;
+            }
+            Value::Object(map) => map,
+            _ => return,
+        };
+
+        let mut issue = |message: String| {
+            issues.push(ValidationIssue {
+                path: path.to_string(),
+                message,
+            })
+        };
+
+        let types = declared_types(schema);
+        if !types.is_empty() && !types.iter().any(|t| matches_type(t, value)) {
+            issue(format!(
+                "expected {}, got {}",
+                types.join(" or "),
+                type_name(value)
+            ));
+            return;
+        }
+
+        if let Some(Value::Array(allowed)) = schema.get("enum") {
+            if !allowed.contains(value) {
+                issue(format!(
+                    "must be one of {}, got {}",
+                    Value::Array(allowed.clone()),
+                    value
+                ));
+            }
+        }
+
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                issue(format!("must be {}, got {}", expected, value));
+            }
+        }
+
+        match value {
+            Value::String(s) => {
+                let len = s.chars().count() as u64;
+                if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                    if len < min {
+                        issue(format!("must be at least {} characters long", min));
+                    }
+                }
+                if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                    if len > max {
+                        issue(format!("must be at most {} characters long", max));
+                    }
+                }
+            }
+            Value::Number(n) => {
+                let n = n.as_f64().unwrap_or_default();
+                if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                    if n < min {
+                        issue(format!("must be >= {}", min));
+                    }
+                }
+                if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                    if n > max {
+                        issue(format!("must be <= {}", max));
+                    }
+                }
+                if let Some(min) = schema.get("exclusiveMinimum").and_then(Value::as_f64) {
+                    if n <= min {
+                        issue(format!("must be > {}", min));
+                    }
+                }
+                if let Some(max) = schema.get("exclusiveMaximum").and_then(Value::as_f64) {
+                    if n >= max {
+                        issue(format!("must be < {}", max));
+                    }
+                }
+            }
+            Value::Array(items) => {
+                let len = items.len() as u64;
+                if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                    if len < min {
+                        issue(format!("must contain at least {} items", min));
+                    }
+                }
+                if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                    if len > max {
+                        issue(format!("must contain at most {} items", max));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        match value {
+            Value::Array(items) => {
+                if let Some(item_schema) = schema.get("items") {
+                    for (index, item) in items.iter().enumerate() {
+                        self.check(
+                            item_schema,
+                            item,
+                            &child_path(path, &index.to_string()),
+                            issues,
+                        );
+                    }
+                }
+            }
+            Value::Object(object) => self.check_object(schema, object, path, issues),
+            _ => {}
+        }
+
+        self.check_combinators(schema, value, path, issues);
+    }
+
+    fn check_object(
+        &self,
+        schema: &'a Map<String, Value>,
+        object: &Map<String, Value>,
+        path: &str,
+        issues: &mut Vec<ValidationIssue>,
+    ) {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    issues.push(ValidationIssue {
+                        path: path.to_string(),
+                        message: format!("missing required property '{}'", name),
+                    });
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, member) in object {
+            let member_path = child_path(path, name);
+            match properties.and_then(|p| p.get(name)) {
+                Some(member_schema) => self.check(member_schema, member, &member_path, issues),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => issues.push(ValidationIssue {
+                        path: member_path,
+                        message: "unexpected property".to_string(),
+                    }),
+                    Some(extra @ Value::Object(_)) => {
+                        self.check(extra, member, &member_path, issues)
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    fn check_combinators(
+        &self,
+        schema: &'a Map<String, Value>,
+        value: &Value,
+        path: &str,
+        issues: &mut Vec<ValidationIssue>,
+    ) {
+        if let Some(Value::Array(all)) = schema.get("allOf") {
+            for branch in all {
+                self.check(branch, value, path, issues);
+            }
+        }
+
+        for keyword in ["anyOf", "oneOf"] {
+            let Some(Value::Array(branches)) = schema.get(keyword) else {
+                continue;
+            };
+            let failures: Vec<Vec<ValidationIssue>> = branches
+                .iter()
+                .map(|branch| {
+                    let mut branch_issues = Vec::new();
+                    self.check(branch, value, path, &mut branch_issues);
+                    branch_issues
+                })
+                .collect();
+            let matching = failures.iter().filter(|f| f.is_empty()).count();
+
+            if matching == 0 {
+                // Report the branch that came closest to matching
+                if let Some(closest) = failures.into_iter().min_by_key(Vec::len) {
+                    issues.push(ValidationIssue {
+                        path: path.to_string(),
+                        message: format!("does not match any allowed schema ({})", keyword),
+                    });
+                    issues.extend(closest);
+                }
+            } else if keyword == "oneOf" && matching > 1 {
+                issues.push(ValidationIssue {
+                    path: path.to_string(),
+                    message: format!("matches {} schemas but must match exactly one", matching),
+                });
+            }
+        }
+    }
+
+    fn coerce(&self, schema: &'a Value, value: &mut Value, path: &str, changes: &mut Vec<String>) {
+        let Some(schema) = self.resolve(schema).as_object() else {
+            return;
+        };
+        let types = declared_types(schema);
+
+        if !types.is_empty() && !types.iter().any(|t| matches_type(t, value)) {
+            if let Some(coerced) = types.iter().find_map(|t| coerce_scalar(t, value)) {
+                changes.push(format!(
+                    "{}: converted {} to {}",
+                    if path.is_empty() { "(root)" } else { path },
+                    type_name(value),
+                    type_name(&coerced)
+                ));
+                *value = coerced;
+            }
+        }
+
+        match value {
+            Value::Array(items) => {
+                if let Some(item_schema) = schema.get("items") {
+                    for (index, item) in items.iter_mut().enumerate() {
+                        self.coerce(
+                            item_schema,
+                            item,
+                            &child_path(path, &index.to_string()),
+                            changes,
+                        );
+                    }
+                }
+            }
+            Value::Object(object) => {
+                if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+                    for (name, member) in object.iter_mut() {
+                        if let Some(member_schema) = properties.get(name) {
+                            self.coerce(member_schema, member, &child_path(path, name), changes);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Convert a value that doesn't match `expected` into one that does, if possible.
+fn coerce_scalar(expected: &str, value: &Value) -> Option<Value> {
+    match (expected, value) {
+        ("integer", Value::String(s)) => s.trim().parse::<i64>().ok().map(Value::from),
+        ("number", Value::String(s)) => s
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number),
+        ("boolean", Value::String(s)) => match s.trim().to_ascii_lowercase().as_str() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        ("string", Value::Number(n)) => Some(Value::String(n.to_string())),
+        ("string", Value::Bool(b)) => Some(Value::String(b.to_string())),
+        ("object", Value::String(s)) | ("array", Value::String(s))
+            if serde_json::from_str::<Value>(s).is_ok_and(|v| matches_type(expected, &v)) =>
+        {
+            serde_json::from_str(s).ok()
+        }
+        ("array", other) if !other.is_null() => Some(Value::Array(vec![other.clone()])),
+        _ => None,
+    }
+}